@@ -0,0 +1,855 @@
+//! Index based tree: all nodes are stored in one `Vec`, nodes refer to each
+//! other by `NodeId` instead of `Rc` / `Weak` pointers
+//!
+//! Compared to `rctree::NodeRef`, there is no allocation per node, traversal
+//! walks through contiguous memory and there are no runtime borrow checks - the
+//! data is borrowed through the arena, so borrow errors are compile errors.
+//!
+//! Ids are generational: when a node is removed, its slot is reused by the next
+//! new node, but with a different generation, so stale ids of removed nodes
+//! don't silently refer to the new node.
+
+use std::ops::{Index, IndexMut};
+use node_data::NodeData;
+use tree_node::{HasNodeId, TreeNode, LayoutTree};
+
+/// Identifies a node in an `Arena`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+impl NodeId {
+
+    /// Position of the node in the arena
+    #[inline]
+    pub fn index(&self)
+    -> usize
+    {
+        self.index as usize
+    }
+}
+
+impl HasNodeId for NodeId {
+    /// Index and generation of the node
+    #[inline]
+    fn node_id(&self)
+    -> u64
+    {
+        u64::from(self.generation) << 32 | u64::from(self.index)
+    }
+}
+
+/// A node with its links to the adjoining nodes
+#[derive(Debug, Clone)]
+struct ArenaNode<T> {
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    /// See `rctree::NodeRef::is_dirty`
+    dirty: bool,
+    data: T,
+}
+
+/// A slot of the arena, empty if the node was removed
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    node: Option<ArenaNode<T>>,
+}
+
+/// Storage for the nodes of one or more trees
+#[derive(Debug, Clone)]
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    /// Indices of the empty slots
+    free: Vec<u32>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default()
+    -> Self
+    {
+        Self { slots: Vec::new(), free: Vec::new() }
+    }
+}
+
+impl<T> Arena<T> {
+
+    /// Creates an empty arena
+    #[inline]
+    pub fn new()
+    -> Self
+    {
+        Self::default()
+    }
+
+    /// Creates an empty arena with space for `capacity` nodes
+    #[inline]
+    pub fn with_capacity(capacity: usize)
+    -> Self
+    {
+        Self { slots: Vec::with_capacity(capacity), free: Vec::new() }
+    }
+
+    /// Number of nodes in the arena
+    #[inline]
+    pub fn len(&self)
+    -> usize
+    {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Returns if the arena doesn't contain any nodes
+    #[inline]
+    pub fn is_empty(&self)
+    -> bool
+    {
+        self.len() == 0
+    }
+
+    /// Creates a new node without a parent
+    pub fn new_node(&mut self, data: T)
+    -> NodeId
+    {
+        let node = ArenaNode {
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+            dirty: true,
+            data,
+        };
+
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: (self.slots.len() - 1) as u32, generation: 0 }
+            },
+        }
+    }
+
+    /// Returns if the node exists (was not removed)
+    #[inline]
+    pub fn contains(&self, id: NodeId)
+    -> bool
+    {
+        self.node(id).is_some()
+    }
+
+    /// Data of the node, `None` if the node was removed
+    #[inline]
+    pub fn get(&self, id: NodeId)
+    -> Option<&T>
+    {
+        self.node(id).map(|node| &node.data)
+    }
+
+    /// Mutable data of the node, `None` if the node was removed.
+    /// Marks the node as dirty, see `is_dirty`.
+    #[inline]
+    pub fn get_mut(&mut self, id: NodeId)
+    -> Option<&mut T>
+    {
+        if !self.contains(id) {
+            return None;
+        }
+        self.mark_dirty(id);
+        self.node_mut(id).map(|node| &mut node.data)
+    }
+
+    /// Returns a handle to the node that implements `TreeNode`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node was removed.
+    #[inline]
+    pub fn node_ref(&self, id: NodeId)
+    -> ArenaRef<'_, T>
+    {
+        assert!(self.contains(id), "node {:?} was removed", id);
+        ArenaRef { arena: self, id }
+    }
+
+    /// Returns the parent of the node, unless the node is the root of its tree
+    #[inline]
+    pub fn parent(&self, id: NodeId)
+    -> Option<NodeId>
+    {
+        self.node(id).and_then(|node| node.parent)
+    }
+
+    /// Returns the first child of the node, unless it has no child
+    #[inline]
+    pub fn first_child(&self, id: NodeId)
+    -> Option<NodeId>
+    {
+        self.node(id).and_then(|node| node.first_child)
+    }
+
+    /// Returns the last child of the node, unless it has no child
+    #[inline]
+    pub fn last_child(&self, id: NodeId)
+    -> Option<NodeId>
+    {
+        self.node(id).and_then(|node| node.last_child)
+    }
+
+    /// Returns the previous sibling of the node, unless it is a first child
+    #[inline]
+    pub fn previous_sibling(&self, id: NodeId)
+    -> Option<NodeId>
+    {
+        self.node(id).and_then(|node| node.previous_sibling)
+    }
+
+    /// Returns the next sibling of the node, unless it is a last child
+    #[inline]
+    pub fn next_sibling(&self, id: NodeId)
+    -> Option<NodeId>
+    {
+        self.node(id).and_then(|node| node.next_sibling)
+    }
+
+    /// Returns an iterator of the node and its ancestors
+    #[inline]
+    pub fn ancestors(&self, id: NodeId)
+    -> Ancestors<'_, T>
+    {
+        Ancestors(self, Some(id).filter(|id| self.contains(*id)))
+    }
+
+    /// Returns an iterator of the node and the siblings before it
+    #[inline]
+    pub fn preceding_siblings(&self, id: NodeId)
+    -> PrecedingSiblings<'_, T>
+    {
+        PrecedingSiblings(self, Some(id).filter(|id| self.contains(*id)))
+    }
+
+    /// Returns an iterator of the node and the siblings after it
+    #[inline]
+    pub fn following_siblings(&self, id: NodeId)
+    -> FollowingSiblings<'_, T>
+    {
+        FollowingSiblings(self, Some(id).filter(|id| self.contains(*id)))
+    }
+
+    /// Returns an iterator of the children of the node
+    #[inline]
+    pub fn children(&self, id: NodeId)
+    -> Children<'_, T>
+    {
+        Children(self, self.first_child(id))
+    }
+
+    /// Returns an iterator of the children of the node, in reverse order
+    #[inline]
+    pub fn reverse_children(&self, id: NodeId)
+    -> ReverseChildren<'_, T>
+    {
+        ReverseChildren(self, self.last_child(id))
+    }
+
+    /// Returns an iterator of the node and its descendants, in tree order
+    #[inline]
+    pub fn descendants(&self, id: NodeId)
+    -> Descendants<'_, T>
+    {
+        Descendants(self.traverse(id))
+    }
+
+    /// Returns an iterator of the start and end of the node and its descendants, in tree order
+    #[inline]
+    pub fn traverse(&self, id: NodeId)
+    -> Traverse<'_, T>
+    {
+        Traverse { arena: self, root: id, next: Some(NodeEdge::Start(id)).filter(|_| self.contains(id)) }
+    }
+
+    /// Returns an iterator of the start and end of the node and its descendants, in reverse tree order
+    #[inline]
+    pub fn reverse_traverse(&self, id: NodeId)
+    -> ReverseTraverse<'_, T>
+    {
+        ReverseTraverse { arena: self, root: id, next: Some(NodeEdge::End(id)).filter(|_| self.contains(id)) }
+    }
+
+    /// Returns whether the node or one of its descendants was changed (borrowed
+    /// mutably, children added or removed) since the last call to `mark_clean`.
+    /// New nodes are dirty.
+    #[inline]
+    pub fn is_dirty(&self, id: NodeId)
+    -> bool
+    {
+        self.node(id).map(|node| node.dirty).unwrap_or(true)
+    }
+
+    /// Marks the node and all of its ancestors as dirty
+    pub fn mark_dirty(&mut self, id: NodeId)
+    {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            current = match self.node_mut(id) {
+                // a dirty node only has dirty ancestors
+                Some(ref node) if node.dirty => None,
+                Some(node) => {
+                    node.dirty = true;
+                    node.parent
+                },
+                None => None,
+            };
+        }
+    }
+
+    /// Clears the dirty flag of the node and all of its dirty descendants
+    pub fn mark_clean(&mut self, id: NodeId)
+    {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.node_mut(id) {
+                node.dirty = false;
+            }
+            let dirty_children = self.children(id).filter(|child| self.is_dirty(*child)).collect::<Vec<_>>();
+            stack.extend(dirty_children);
+        }
+    }
+
+    /// Detaches the node from its parent and siblings. Children are not affected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node was removed.
+    pub fn detach(&mut self, id: NodeId)
+    {
+        let (parent, previous_sibling, next_sibling) = {
+            let node = self.expect_node_mut(id);
+            (node.parent.take(), node.previous_sibling.take(), node.next_sibling.take())
+        };
+
+        if let Some(parent) = parent {
+            self.mark_dirty(parent);
+        }
+
+        match next_sibling {
+            Some(next_sibling) => self.expect_node_mut(next_sibling).previous_sibling = previous_sibling,
+            None => if let Some(parent) = parent { self.expect_node_mut(parent).last_child = previous_sibling; },
+        }
+
+        match previous_sibling {
+            Some(previous_sibling) => self.expect_node_mut(previous_sibling).next_sibling = next_sibling,
+            None => if let Some(parent) = parent { self.expect_node_mut(parent).first_child = next_sibling; },
+        }
+    }
+
+    /// Appends a new child to the node, after existing children.
+    /// The new child is detached from its previous position first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the nodes was removed.
+    pub fn append(&mut self, id: NodeId, new_child: NodeId)
+    {
+        self.detach(new_child);
+        self.mark_dirty(id);
+
+        let last_child = self.expect_node_mut(id).last_child.replace(new_child);
+        {
+            let child = self.expect_node_mut(new_child);
+            child.parent = Some(id);
+            child.previous_sibling = last_child;
+        }
+        match last_child {
+            Some(last_child) => self.expect_node_mut(last_child).next_sibling = Some(new_child),
+            None => self.expect_node_mut(id).first_child = Some(new_child),
+        }
+    }
+
+    /// Prepends a new child to the node, before existing children.
+    /// The new child is detached from its previous position first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the nodes was removed.
+    pub fn prepend(&mut self, id: NodeId, new_child: NodeId)
+    {
+        self.detach(new_child);
+        self.mark_dirty(id);
+
+        let first_child = self.expect_node_mut(id).first_child.replace(new_child);
+        {
+            let child = self.expect_node_mut(new_child);
+            child.parent = Some(id);
+            child.next_sibling = first_child;
+        }
+        match first_child {
+            Some(first_child) => self.expect_node_mut(first_child).previous_sibling = Some(new_child),
+            None => self.expect_node_mut(id).last_child = Some(new_child),
+        }
+    }
+
+    /// Inserts a new sibling after the node.
+    /// The new sibling is detached from its previous position first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the nodes was removed.
+    pub fn insert_after(&mut self, id: NodeId, new_sibling: NodeId)
+    {
+        self.detach(new_sibling);
+
+        let parent = self.expect_node_mut(id).parent;
+        if let Some(parent) = parent {
+            self.mark_dirty(parent);
+        }
+
+        let next_sibling = self.expect_node_mut(id).next_sibling.replace(new_sibling);
+        {
+            let sibling = self.expect_node_mut(new_sibling);
+            sibling.parent = parent;
+            sibling.previous_sibling = Some(id);
+            sibling.next_sibling = next_sibling;
+        }
+        match next_sibling {
+            Some(next_sibling) => self.expect_node_mut(next_sibling).previous_sibling = Some(new_sibling),
+            None => if let Some(parent) = parent { self.expect_node_mut(parent).last_child = Some(new_sibling); },
+        }
+    }
+
+    /// Inserts a new sibling before the node.
+    /// The new sibling is detached from its previous position first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the nodes was removed.
+    pub fn insert_before(&mut self, id: NodeId, new_sibling: NodeId)
+    {
+        self.detach(new_sibling);
+
+        let parent = self.expect_node_mut(id).parent;
+        if let Some(parent) = parent {
+            self.mark_dirty(parent);
+        }
+
+        let previous_sibling = self.expect_node_mut(id).previous_sibling.replace(new_sibling);
+        {
+            let sibling = self.expect_node_mut(new_sibling);
+            sibling.parent = parent;
+            sibling.previous_sibling = previous_sibling;
+            sibling.next_sibling = Some(id);
+        }
+        match previous_sibling {
+            Some(previous_sibling) => self.expect_node_mut(previous_sibling).next_sibling = Some(new_sibling),
+            None => if let Some(parent) = parent { self.expect_node_mut(parent).first_child = Some(new_sibling); },
+        }
+    }
+
+    /// Detaches the node and removes it and all of its descendants from the arena.
+    /// Returns the data of the node, `None` if the node was already removed.
+    pub fn remove(&mut self, id: NodeId)
+    -> Option<T>
+    {
+        if !self.contains(id) {
+            return None;
+        }
+
+        self.detach(id);
+        let descendants = self.descendants(id).collect::<Vec<_>>();
+
+        let mut data = None;
+        for descendant in descendants {
+            let slot = &mut self.slots[descendant.index as usize];
+            let node = slot.node.take();
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(descendant.index);
+            if descendant == id {
+                data = node.map(|node| node.data);
+            }
+        }
+        data
+    }
+
+    #[inline]
+    fn node(&self, id: NodeId)
+    -> Option<&ArenaNode<T>>
+    {
+        self.slots.get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    #[inline]
+    fn node_mut(&mut self, id: NodeId)
+    -> Option<&mut ArenaNode<T>>
+    {
+        self.slots.get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    #[inline]
+    fn expect_node_mut(&mut self, id: NodeId)
+    -> &mut ArenaNode<T>
+    {
+        match self.node_mut(id) {
+            Some(node) => node,
+            None => panic!("node {:?} was removed", id),
+        }
+    }
+}
+
+/// # Panics
+///
+/// Panics if the node was removed.
+impl<T> Index<NodeId> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: NodeId)
+    -> &T
+    {
+        match self.get(id) {
+            Some(data) => data,
+            None => panic!("node {:?} was removed", id),
+        }
+    }
+}
+
+/// Marks the node as dirty, see `Arena::get_mut`
+///
+/// # Panics
+///
+/// Panics if the node was removed.
+impl<T> IndexMut<NodeId> for Arena<T> {
+    fn index_mut(&mut self, id: NodeId)
+    -> &mut T
+    {
+        match self.get_mut(id) {
+            Some(data) => data,
+            None => panic!("node {:?} was removed", id),
+        }
+    }
+}
+
+macro_rules! impl_arena_iterator {
+    ($name: ident, $next: ident) => {
+        impl<'a, T> Iterator for $name<'a, T> {
+            type Item = NodeId;
+
+            fn next(&mut self) -> Option<NodeId> {
+                let id = self.1.take()?;
+                self.1 = self.0.$next(id);
+                Some(id)
+            }
+        }
+    }
+}
+
+/// An iterator of the ids of a node and its ancestors
+pub struct Ancestors<'a, T: 'a>(&'a Arena<T>, Option<NodeId>);
+impl_arena_iterator!(Ancestors, parent);
+
+/// An iterator of the ids of a node and the siblings before it
+pub struct PrecedingSiblings<'a, T: 'a>(&'a Arena<T>, Option<NodeId>);
+impl_arena_iterator!(PrecedingSiblings, previous_sibling);
+
+/// An iterator of the ids of a node and the siblings after it
+pub struct FollowingSiblings<'a, T: 'a>(&'a Arena<T>, Option<NodeId>);
+impl_arena_iterator!(FollowingSiblings, next_sibling);
+
+/// An iterator of the ids of the children of a node
+pub struct Children<'a, T: 'a>(&'a Arena<T>, Option<NodeId>);
+impl_arena_iterator!(Children, next_sibling);
+
+/// An iterator of the ids of the children of a node, in reverse order
+pub struct ReverseChildren<'a, T: 'a>(&'a Arena<T>, Option<NodeId>);
+impl_arena_iterator!(ReverseChildren, previous_sibling);
+
+/// An iterator of the ids of a node and its descendants, in tree order
+pub struct Descendants<'a, T: 'a>(Traverse<'a, T>);
+
+impl<'a, T> Iterator for Descendants<'a, T> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        loop {
+            match self.0.next()? {
+                NodeEdge::Start(id) => return Some(id),
+                NodeEdge::End(_) => {},
+            }
+        }
+    }
+}
+
+/// Start or end of a node, see `rctree::NodeEdge`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NodeEdge {
+    /// Yielded before the descendants of the node
+    Start(NodeId),
+    /// Yielded after the descendants of the node
+    End(NodeId),
+}
+
+/// An iterator of the start and end of a node and its descendants, in tree order
+pub struct Traverse<'a, T: 'a> {
+    arena: &'a Arena<T>,
+    root: NodeId,
+    next: Option<NodeEdge>,
+}
+
+impl<'a, T> Iterator for Traverse<'a, T> {
+    type Item = NodeEdge;
+
+    fn next(&mut self) -> Option<NodeEdge> {
+        let item = self.next.take()?;
+        self.next = match item {
+            NodeEdge::Start(id) => match self.arena.first_child(id) {
+                Some(first_child) => Some(NodeEdge::Start(first_child)),
+                None => Some(NodeEdge::End(id)),
+            },
+            NodeEdge::End(id) if id == self.root => None,
+            NodeEdge::End(id) => match self.arena.next_sibling(id) {
+                Some(next_sibling) => Some(NodeEdge::Start(next_sibling)),
+                None => self.arena.parent(id).map(NodeEdge::End),
+            },
+        };
+        Some(item)
+    }
+}
+
+/// An iterator of the start and end of a node and its descendants, in reverse tree order
+pub struct ReverseTraverse<'a, T: 'a> {
+    arena: &'a Arena<T>,
+    root: NodeId,
+    next: Option<NodeEdge>,
+}
+
+impl<'a, T> Iterator for ReverseTraverse<'a, T> {
+    type Item = NodeEdge;
+
+    fn next(&mut self) -> Option<NodeEdge> {
+        let item = self.next.take()?;
+        self.next = match item {
+            NodeEdge::End(id) => match self.arena.last_child(id) {
+                Some(last_child) => Some(NodeEdge::End(last_child)),
+                None => Some(NodeEdge::Start(id)),
+            },
+            NodeEdge::Start(id) if id == self.root => None,
+            NodeEdge::Start(id) => match self.arena.previous_sibling(id) {
+                Some(previous_sibling) => Some(NodeEdge::End(previous_sibling)),
+                None => self.arena.parent(id).map(NodeEdge::Start),
+            },
+        };
+        Some(item)
+    }
+}
+
+/// A node of an arena, borrowing the arena. Implements `TreeNode`, so the
+/// layout can walk the arena like a tree of `NodeRef`s.
+#[derive(Debug)]
+pub struct ArenaRef<'a, T: 'a> {
+    arena: &'a Arena<T>,
+    id: NodeId,
+}
+
+// not derived, that would require `T: Clone`
+impl<'a, T> Clone for ArenaRef<'a, T> {
+    fn clone(&self)
+    -> Self
+    {
+        *self
+    }
+}
+
+impl<'a, T> Copy for ArenaRef<'a, T> { }
+
+impl<'a, T> ArenaRef<'a, T> {
+
+    /// Id of the node
+    #[inline]
+    pub fn id(&self)
+    -> NodeId
+    {
+        self.id
+    }
+
+    /// The arena that contains the node
+    #[inline]
+    pub fn arena(&self)
+    -> &'a Arena<T>
+    {
+        self.arena
+    }
+
+    #[inline]
+    fn with_id(&self, id: Option<NodeId>)
+    -> Option<Self>
+    {
+        id.map(|id| ArenaRef { arena: self.arena, id })
+    }
+}
+
+impl<'a, T> HasNodeId for ArenaRef<'a, T> {
+    #[inline]
+    fn node_id(&self)
+    -> u64
+    {
+        self.id.node_id()
+    }
+}
+
+impl<'a, T> TreeNode for ArenaRef<'a, T> {
+    type Data = T;
+    type Handle = NodeId;
+    type Ref<'b> = &'b T where Self: 'b;
+
+    #[inline]
+    fn borrow(&self)
+    -> &T
+    {
+        &self.arena[self.id]
+    }
+
+    #[inline]
+    fn parent(&self)
+    -> Option<Self>
+    {
+        self.with_id(self.arena.parent(self.id))
+    }
+
+    #[inline]
+    fn first_child(&self)
+    -> Option<Self>
+    {
+        self.with_id(self.arena.first_child(self.id))
+    }
+
+    #[inline]
+    fn last_child(&self)
+    -> Option<Self>
+    {
+        self.with_id(self.arena.last_child(self.id))
+    }
+
+    #[inline]
+    fn previous_sibling(&self)
+    -> Option<Self>
+    {
+        self.with_id(self.arena.previous_sibling(self.id))
+    }
+
+    #[inline]
+    fn next_sibling(&self)
+    -> Option<Self>
+    {
+        self.with_id(self.arena.next_sibling(self.id))
+    }
+
+    #[inline]
+    fn is_dirty(&self)
+    -> bool
+    {
+        self.arena.is_dirty(self.id)
+    }
+
+    #[inline]
+    fn handle(&self)
+    -> NodeId
+    {
+        self.id
+    }
+}
+
+/// An arena of `NodeData` together with the root of the UI tree, see `UiScreen::from_tree`
+#[derive(Debug, Clone)]
+pub struct ArenaTree<T> {
+    /// Storage of all nodes, the nodes that are not below `root` are not laid out
+    pub arena: Arena<NodeData<T>>,
+    /// Root node of the UI tree
+    pub root: NodeId,
+}
+
+impl<T> ArenaTree<T> {
+
+    /// Creates a tree that only contains the root node
+    #[inline]
+    pub fn new(root: NodeData<T>)
+    -> Self
+    {
+        let mut arena = Arena::new();
+        let root = arena.new_node(root);
+        Self { arena, root }
+    }
+}
+
+impl<T> LayoutTree for ArenaTree<T> {
+    type Data = T;
+    type Handle = NodeId;
+    type Node<'a> = ArenaRef<'a, NodeData<T>> where Self: 'a;
+
+    #[inline]
+    fn root_node(&self)
+    -> ArenaRef<'_, NodeData<T>>
+    {
+        self.arena.node_ref(self.root)
+    }
+
+    #[inline]
+    fn update_root<F>(&mut self, f: F)
+    where F: FnOnce(&mut NodeData<T>)
+    {
+        f(&mut self.arena[self.root]);
+    }
+
+    #[inline]
+    fn mark_clean(&mut self)
+    {
+        self.arena.mark_clean(self.root);
+    }
+}
+
+#[test]
+fn arena_navigation_and_stale_ids() {
+    let mut arena = Arena::new();
+    let a = arena.new_node(1);
+    let b = arena.new_node(2);
+    let c = arena.new_node(3);
+    let d = arena.new_node(4);
+    arena.append(a, b);
+    arena.append(a, c);
+    arena.prepend(a, d);
+    let e = arena.new_node(5);
+    arena.insert_after(d, e);
+    let f = arena.new_node(6);
+    arena.append(b, f);
+
+    let data = |arena: &Arena<u32>, ids: Vec<NodeId>| ids.into_iter().map(|id| arena[id]).collect::<Vec<_>>();
+    assert_eq!(data(&arena, arena.descendants(a).collect()), vec![1, 4, 5, 2, 6, 3]);
+    assert_eq!(data(&arena, arena.reverse_children(a).collect()), vec![3, 2, 5, 4]);
+    assert_eq!(data(&arena, arena.ancestors(arena.last_child(b).unwrap()).collect()), vec![6, 2, 1]);
+    assert_eq!(arena.node_ref(a).descendants().map(|node| *node.borrow()).collect::<Vec<_>>(), vec![1, 4, 5, 2, 6, 3]);
+
+    // removing a node removes its descendants, the slots are reused with a new generation
+    arena.mark_clean(a);
+    assert_eq!(arena.remove(b), Some(2));
+    assert!(arena.is_dirty(a) && !arena.is_dirty(c));
+    assert_eq!(arena.len(), 4);
+    assert_eq!(data(&arena, arena.children(a).collect()), vec![4, 5, 3]);
+
+    let g = arena.new_node(7);
+    let h = arena.new_node(8);
+    assert!(arena.get(b).is_none() && arena.get(f).is_none() && arena.parent(b).is_none());
+    assert_eq!(g.index(), f.index());
+    assert_eq!((arena[g], arena[h]), (7, 8));
+    // same index in the lower half of the id, the generation is in the upper half
+    assert_eq!(g.node_id() as u32, f.node_id() as u32);
+    assert_ne!(g.node_id(), f.node_id());
+}
@@ -0,0 +1,72 @@
+//! Baselines of laid out nodes
+//!
+//! Leaf nodes report their baseline via `NodeData::baseline`, containers take
+//! the first / last baseline of their children. All baselines are measured from
+//! the top edge of the node they belong to.
+
+use tree_node::TreeNode;
+use node_data::NodeData;
+use layout_result::LayoutResults;
+
+/// Returns the first baseline of an already laid out node, if it has one
+pub fn first_baseline<T, N: TreeNode<Data = NodeData<T>>>(node: &N, results: &LayoutResults)
+-> Option<f32>
+{
+    if let Some(baseline) = node.borrow().baseline {
+        return Some(baseline);
+    }
+
+    let top = results.get(node)?.bounds.top;
+    node.children()
+        .filter_map(|child| first_baseline(&child, results).map(|b| b + top_of(&child, results) - top))
+        .next()
+}
+
+/// Returns the last baseline of an already laid out node, if it has one
+pub fn last_baseline<T, N: TreeNode<Data = NodeData<T>>>(node: &N, results: &LayoutResults)
+-> Option<f32>
+{
+    if let Some(baseline) = node.borrow().baseline {
+        return Some(baseline);
+    }
+
+    let top = results.get(node)?.bounds.top;
+    node.reverse_children()
+        .filter_map(|child| last_baseline(&child, results).map(|b| b + top_of(&child, results) - top))
+        .next()
+}
+
+/// Shifts the children of a row down so that their first baselines line up.
+///
+/// Children without a baseline are not moved.
+pub fn align_children_to_baseline<T, N: TreeNode<Data = NodeData<T>>>(row: &N, results: &mut LayoutResults)
+{
+    let baselines = row.children()
+        .map(|child| first_baseline(&child, results).map(|b| b + top_of(&child, results)))
+        .collect::<Vec<_>>();
+
+    if baselines.iter().all(|b| b.is_none()) {
+        return;
+    }
+
+    let max_baseline = baselines.iter().filter_map(|b| *b).fold(f32::MIN, f32::max);
+
+    for (child, baseline) in row.children().zip(baselines) {
+        let shift = match baseline {
+            Some(b) => max_baseline - b,
+            None => continue,
+        };
+
+        if shift != 0.0 {
+            results.translate_subtree(&child, 0.0, shift);
+        }
+    }
+}
+
+/// Top edge of the layout box of a node
+#[inline]
+fn top_of<T, N: TreeNode<Data = NodeData<T>>>(node: &N, results: &LayoutResults)
+-> f32
+{
+    results.get(node).map(|result| result.bounds.top).unwrap_or(0.0)
+}
@@ -0,0 +1,108 @@
+//! Conversion of points and boxes between screen space and the local space of a node
+//!
+//! The local space of a node has its origin at the top left corner of the node,
+//! before scrolling, rotations and transforms are applied, i.e. a click at local
+//! (5, 5) is always 5 units right and down from the corner of a button, no matter
+//! if the button is inside of a rotated panel or a scrolled list.
+//!
+//! All functions work on the computed layout of a node (`UiScreen::layout`),
+//! see `LayoutResult::to_screen`.
+
+use rect::Bounds;
+use layout_result::LayoutResult;
+use transform::Transform2D;
+
+/// Maps a point from the local space of the node to screen space
+#[inline]
+pub fn to_global(node: &LayoutResult, x: f32, y: f32)
+-> (f32, f32)
+{
+    node.to_screen.transform_point(x, y)
+}
+
+/// Maps a point from screen space to the local space of the node.
+/// Returns `None` if the node was squashed to a line by a transform.
+#[inline]
+pub fn to_local(node: &LayoutResult, x: f32, y: f32)
+-> Option<(f32, f32)>
+{
+    node.to_screen.inverse().map(|to_local| to_local.transform_point(x, y))
+}
+
+/// Maps a point from the local space of `from` to the local space of `to`
+#[inline]
+pub fn between(from: &LayoutResult, to: &LayoutResult, x: f32, y: f32)
+-> Option<(f32, f32)>
+{
+    transform_between(from, to).map(|transform| transform.transform_point(x, y))
+}
+
+/// The transform that maps the local space of `from` to the local space of `to`
+#[inline]
+pub fn transform_between(from: &LayoutResult, to: &LayoutResult)
+-> Option<Transform2D>
+{
+    to.to_screen.inverse().map(|to_local| from.to_screen.then(&to_local))
+}
+
+/// Maps a box in the local space of the node to screen space. The result
+/// can be rotated, so the corners are returned (x, y), each tl, tr, bl, br.
+#[inline]
+pub fn bounds_to_global(node: &LayoutResult, bounds: &Bounds)
+-> ([f32; 4], [f32; 4])
+{
+    transform_corners(&node.to_screen, bounds)
+}
+
+/// Maps a box in screen space to the local space of the node, see `bounds_to_global`
+#[inline]
+pub fn bounds_to_local(node: &LayoutResult, bounds: &Bounds)
+-> Option<([f32; 4], [f32; 4])>
+{
+    node.to_screen.inverse().map(|to_local| transform_corners(&to_local, bounds))
+}
+
+/// Transforms the four corners of a box
+fn transform_corners(transform: &Transform2D, bounds: &Bounds)
+-> ([f32; 4], [f32; 4])
+{
+    let corners = [(bounds.left, bounds.top), (bounds.right(), bounds.top),
+                   (bounds.left, bounds.bottom()), (bounds.right(), bounds.bottom())];
+
+    let mut x = [0.0; 4];
+    let mut y = [0.0; 4];
+    for (i, &(corner_x, corner_y)) in corners.iter().enumerate() {
+        let (new_x, new_y) = transform.transform_point(corner_x, corner_y);
+        x[i] = new_x;
+        y[i] = new_y;
+    }
+    (x, y)
+}
+
+#[test]
+fn local_coordinates_inside_rotated_and_scrolled_node() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection, Overflow};
+    use ui_screen::UiScreen;
+
+    let root = NodeData::empty(FlexDirection::Row, 0).with_overflow(Overflow::Scroll);
+    let mut ui_screen = UiScreen::new(100.0, 100.0, root);
+    ui_screen.root.borrow_mut().scroll_offset = (0.0, 10.0);
+
+    // rotated by 90 degrees around its center, so the top left corner ends up top right
+    let panel = NodeData::empty(FlexDirection::Row, 1).with_transform(Transform2D::rotation(90.0));
+    let panel = NodeRef::new(panel);
+    ui_screen.root.append(panel.clone());
+    ui_screen.into_rectangles(100.0, 100.0);
+
+    let root = ui_screen.layout(&ui_screen.root).unwrap();
+    let panel = ui_screen.layout(&panel).unwrap();
+    let (x, y) = to_global(panel, 0.0, 0.0);
+    assert!((x - 100.0).abs() < 0.001 && (y + 10.0).abs() < 0.001);
+
+    let (x, y) = to_local(panel, 90.0, 10.0).unwrap();
+    assert!((x - 20.0).abs() < 0.001 && (y - 10.0).abs() < 0.001);
+
+    let (x, y) = between(panel, root, 0.0, 0.0).unwrap();
+    assert!((x - 100.0).abs() < 0.001 && (y + 10.0).abs() < 0.001);
+}
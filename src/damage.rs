@@ -0,0 +1,235 @@
+//! Damage regions: the parts of the screen that changed between two layouts
+//!
+//! Take a `LayoutSnapshot` after every layout and compare it with the snapshot
+//! of the previous layout. Only the returned regions have to be redrawn.
+//!
+//! Only the geometry and the z-index are compared - if the data of a node
+//! changes (i.e. a different color), the node has to be redrawn separately.
+
+use std::collections::HashMap;
+use tree_node::TreeNode;
+use node_data::NodeData;
+use rect::{Bounds, corners_bounding_box};
+use layout_result::LayoutResults;
+
+/// Geometry of a single node at the time of the snapshot
+#[derive(Debug, Clone)]
+struct SnapshotEntry<H> {
+    node: H,
+    /// `HasNodeId::node_id` of the node
+    id: u64,
+    x: [f32; 4],
+    y: [f32; 4],
+    z: f32,
+}
+
+/// The computed geometry of all nodes of a tree, without the data of the nodes
+#[derive(Debug, Clone)]
+pub struct LayoutSnapshot<H> {
+    entries: Vec<SnapshotEntry<H>>,
+    /// `HasNodeId::node_id` -> index in `entries`. The snapshot keeps `NodeRef`s
+    /// alive, so the ids can't be reused in the meantime. Arena ids contain
+    /// the generation of the node, so they aren't reused either.
+    ids: HashMap<u64, usize>,
+}
+
+impl<H: Clone> LayoutSnapshot<H> {
+
+    /// Takes a snapshot of the tree below `root` (including the root).
+    /// Nodes that weren't laid out are skipped.
+    pub fn capture<T, N>(root: &N, results: &LayoutResults)
+    -> Self
+    where N: TreeNode<Data = NodeData<T>, Handle = H>
+    {
+        let mut entries = Vec::new();
+        let mut ids = HashMap::new();
+
+        for node in root.descendants() {
+            let (x, y, z) = match results.get(&node) {
+                Some(result) => (result.x, result.y, result.z),
+                None => continue,
+            };
+            let id = node.node_id();
+            ids.insert(id, entries.len());
+            entries.push(SnapshotEntry { node: node.handle(), id, x, y, z });
+        }
+
+        Self { entries, ids }
+    }
+
+    /// Number of nodes in the snapshot
+    #[inline]
+    pub fn len(&self)
+    -> usize
+    {
+        self.entries.len()
+    }
+
+    /// Returns if the snapshot doesn't contain any nodes
+    #[inline]
+    pub fn is_empty(&self)
+    -> bool
+    {
+        self.entries.is_empty()
+    }
+}
+
+/// What happened to a node between two layouts
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// The node was not in the previous layout
+    Added,
+    /// The node is not in the current layout anymore
+    Removed,
+    /// Same size and rotation, different position
+    Moved,
+    /// Different size or rotation (and maybe a different position)
+    Resized,
+    /// Same geometry, different z-index
+    Restacked,
+}
+
+/// A node that changed between two layouts
+#[derive(Debug, Clone)]
+pub struct NodeChange<H> {
+    pub node: H,
+    pub kind: ChangeKind,
+    /// Bounding box in the previous layout, `None` if the node was added
+    pub before: Option<Bounds>,
+    /// Bounding box in the current layout, `None` if the node was removed
+    pub after: Option<Bounds>,
+}
+
+/// Returns all nodes that were added, removed, moved, resized or restacked between
+/// the two snapshots. Added and changed nodes are in the order of `current`,
+/// followed by the removed nodes in the order of `previous`.
+pub fn changes<H: Clone>(previous: &LayoutSnapshot<H>, current: &LayoutSnapshot<H>)
+-> Vec<NodeChange<H>>
+{
+    let mut changes = Vec::new();
+
+    for entry in &current.entries {
+        let old_entry = match previous.ids.get(&entry.id) {
+            Some(index) => &previous.entries[*index],
+            None => {
+                changes.push(NodeChange { node: entry.node.clone(), kind: ChangeKind::Added,
+                                          before: None, after: Some(corners_bounding_box(&entry.x, &entry.y)) });
+                continue;
+            },
+        };
+
+        let kind = if old_entry.x == entry.x && old_entry.y == entry.y {
+            if old_entry.z == entry.z { continue; }
+            ChangeKind::Restacked
+        } else if is_translation(old_entry, entry) {
+            ChangeKind::Moved
+        } else {
+            ChangeKind::Resized
+        };
+
+        changes.push(NodeChange { node: entry.node.clone(), kind,
+                                  before: Some(corners_bounding_box(&old_entry.x, &old_entry.y)), after: Some(corners_bounding_box(&entry.x, &entry.y)) });
+    }
+
+    for entry in &previous.entries {
+        if !current.ids.contains_key(&entry.id) {
+            changes.push(NodeChange { node: entry.node.clone(), kind: ChangeKind::Removed,
+                                      before: Some(corners_bounding_box(&entry.x, &entry.y)), after: None });
+        }
+    }
+
+    changes
+}
+
+/// Returns the regions of the screen that have to be redrawn. For a moved node,
+/// both the old and the new position are damaged.
+///
+/// If `coalesce` is set, overlapping and touching regions are merged into
+/// their union, so the result doesn't contain any overlapping regions.
+pub fn damage_regions<H: Clone>(previous: &LayoutSnapshot<H>, current: &LayoutSnapshot<H>, coalesce: bool)
+-> Vec<Bounds>
+{
+    let mut regions = Vec::new();
+
+    for change in changes(previous, current) {
+        for bounds in change.before.iter().chain(change.after.iter()) {
+            if bounds.area() > 0.0 && !regions.iter().any(|region: &Bounds| region.contains(bounds)) {
+                regions.push(*bounds);
+            }
+        }
+    }
+
+    if coalesce {
+        coalesce_regions(&mut regions);
+    }
+
+    regions
+}
+
+/// Merges overlapping or touching regions until no two regions overlap
+fn coalesce_regions(regions: &mut Vec<Bounds>)
+{
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..regions.len() {
+            for j in (i + 1)..regions.len() {
+                if touches(&regions[i], &regions[j]) {
+                    regions[i] = regions[i].union(&regions[j]);
+                    regions.swap_remove(j);
+                    merged = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+}
+
+/// Tests if the boxes overlap or share an edge
+#[inline]
+fn touches(a: &Bounds, b: &Bounds)
+-> bool
+{
+    a.left <= b.right() && b.left <= a.right() && a.top <= b.bottom() && b.top <= a.bottom()
+}
+
+/// Tests if `b` is `a` moved by some offset
+#[inline]
+fn is_translation<H>(a: &SnapshotEntry<H>, b: &SnapshotEntry<H>)
+-> bool
+{
+    let dx = b.x[0] - a.x[0];
+    let dy = b.y[0] - a.y[0];
+    (1..4).all(|i| b.x[i] - a.x[i] == dx && b.y[i] - a.y[i] == dy)
+}
+
+#[test]
+fn damage_covers_old_and_new_position() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection};
+    use ui_screen::UiScreen;
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let mut fixed = NodeData::empty(FlexDirection::Row, 1);
+    fixed.width = Some(20.0);
+    let first = NodeRef::new(fixed.clone());
+    ui_screen.root.append(first.clone());
+    ui_screen.root.append(NodeRef::new(fixed));
+
+    ui_screen.into_rectangles(100.0, 100.0);
+    let previous = ui_screen.snapshot();
+
+    // nothing changed
+    ui_screen.into_rectangles(100.0, 100.0);
+    assert!(damage_regions(&previous, &ui_screen.snapshot(), true).is_empty());
+
+    // removing the first node moves the second node to the left
+    first.detach();
+    ui_screen.into_rectangles(100.0, 100.0);
+    let current = ui_screen.snapshot();
+
+    let changes = changes(&previous, &current);
+    assert_eq!(changes.iter().map(|c| c.kind).collect::<Vec<_>>(), vec![ChangeKind::Moved, ChangeKind::Removed]);
+    assert_eq!(damage_regions(&previous, &current, false).len(), 2);
+    assert_eq!(damage_regions(&previous, &current, true), vec![Bounds::new(0.0, 0.0, 40.0, 100.0)]);
+}
@@ -0,0 +1,117 @@
+//! Hit testing: finding the topmost node under a point
+//!
+//! Works on the computed layout of the nodes, so it has to be called after
+//! `UiScreen::into_rectangles`. Rotated and transformed
+//! nodes are tested against their actual corners, not their bounding box.
+
+use tree_node::TreeNode;
+use node_data::{NodeData, Overflow};
+use layout_result::LayoutResults;
+
+/// The node that was hit
+#[derive(Debug)]
+pub struct HitTestResult<H> {
+    /// The topmost node under the point
+    pub node: H,
+    /// The node and all of its ancestors, starting with the root
+    pub path: Vec<H>,
+}
+
+/// Returns the topmost node under (x, y), or `None` if no node was hit.
+///
+/// Nodes with a higher z-index win, for nodes with the same z-index the one that
+/// comes later in tree order wins. Nodes that have `pointer_events` disabled or
+/// an area of zero are skipped. Children of `Overflow::Hidden` / `Overflow::Scroll` nodes can only be
+/// hit inside of the visible area of their parent.
+pub fn hit_test<T, N: TreeNode<Data = NodeData<T>>>(root: &N, results: &LayoutResults, x: f32, y: f32)
+-> Option<HitTestResult<N::Handle>>
+{
+    let mut best: Option<(f32, N)> = None;
+    visit(root, results, x, y, &mut best);
+
+    best.map(|(_, node)| {
+        let mut path = node.ancestors().map(|node| node.handle()).collect::<Vec<_>>();
+        path.reverse();
+        HitTestResult { node: node.handle(), path }
+    })
+}
+
+fn visit<T, N: TreeNode<Data = NodeData<T>>>(node: &N, results: &LayoutResults, x: f32, y: f32,
+                                             best: &mut Option<(f32, N)>)
+{
+    let result = match results.get(node) {
+        Some(result) => result,
+        None => return,
+    };
+    let data = node.borrow();
+
+    let is_inside = result.contains_point(x, y);
+    // empty nodes can't be hit, even if the point is exactly on their corner
+    if is_inside && result.area() > 0.0 && data.pointer_events && best.as_ref().is_none_or(|&(z, _)| result.z >= z) {
+        *best = Some((result.z, node.clone()));
+    }
+
+    // the point is outside of the visible area of the children
+    if data.overflow != Overflow::Visible && !is_inside {
+        return;
+    }
+
+    for child in node.children() {
+        visit(&child, results, x, y, best);
+    }
+}
+
+#[test]
+fn hit_test_respects_clipping_and_pointer_events() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection};
+    use ui_screen::UiScreen;
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+
+    // left half clips its child, which is wider than the clipping node
+    let clip = NodeRef::new(NodeData::empty(FlexDirection::Row, 1).with_overflow(Overflow::Hidden));
+    let mut wide = NodeData::empty(FlexDirection::Row, 2);
+    wide.width = Some(100.0);
+    clip.append(NodeRef::new(wide));
+    ui_screen.root.append(clip);
+
+    // right half can't be hit, only the root behind it
+    let ghost = NodeData::empty(FlexDirection::Row, 3).without_pointer_events();
+    ui_screen.root.append(NodeRef::new(ghost));
+
+    ui_screen.into_rectangles(100.0, 100.0);
+
+    let hit = ui_screen.hit_test(25.0, 50.0).unwrap();
+    assert_eq!(hit.node.borrow().data, 2);
+    assert_eq!(hit.path.iter().map(|n| n.borrow().data).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+    let hit = ui_screen.hit_test(75.0, 50.0).unwrap();
+    assert_eq!(hit.node.borrow().data, 0);
+
+    assert!(ui_screen.hit_test(150.0, 50.0).is_none());
+}
+
+#[test]
+fn empty_nodes_are_never_hit() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection};
+    use ui_screen::UiScreen;
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    ui_screen.root.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 1)));
+
+    // appended after the real node, so it would win on the same z-index
+    let mut empty = NodeData::empty(FlexDirection::Row, 2);
+    empty.width = Some(0.0);
+    empty.height = Some(0.0);
+    let empty = NodeRef::new(empty);
+    ui_screen.root.append(empty.clone());
+
+    ui_screen.into_rectangles(100.0, 100.0);
+
+    let corner = ui_screen.layout(&empty).unwrap().bounds;
+    let hit = ui_screen.hit_test(corner.left, corner.top).unwrap();
+    assert_eq!(hit.node.borrow().data, 1);
+    assert_eq!(ui_screen.hit_test(50.0, 50.0).unwrap().node.borrow().data, 1);
+}
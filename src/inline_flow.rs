@@ -0,0 +1,164 @@
+//! Inline flow layout: children are placed left to right and break to a new
+//! line when the width of the container is exhausted (chips, text spans, icons)
+
+use tree_node::TreeNode;
+use node_data::{NodeData, InlineLayout, TextAlign, VerticalAlign};
+use rect::Bounds;
+use measure_cache::MeasureCache;
+use std::mem;
+
+/// A child of the inline container with its size, before it is put on a line
+struct InlineItem<N> {
+    node: N,
+    width: f32,
+    height: f32,
+    baseline: f32,
+}
+
+/// Calculates the position of the children of an inline container inside of `bounds`.
+/// Every child gets its max-content size, children that are wider than the
+/// container get a line for themselves and overflow.
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(container: &N, bounds: Bounds, layout: &InlineLayout,
+                                                   cache: &mut MeasureCache)
+-> Vec<(N, Bounds)>
+{
+    let items = container.children().map(|node| {
+        let width = cache.max_content_width(&node);
+        let height = cache.max_content_height_at(&node, width);
+        // children without a baseline sit on the baseline with their bottom edge
+        let baseline = node.borrow().baseline.unwrap_or(height);
+        InlineItem { node, width, height, baseline }
+    }).collect();
+
+    let mut placed = Vec::new();
+    let mut line_top = bounds.top;
+
+    for mut line in break_lines(items, bounds.width) {
+        let line_width = line.iter().map(|item| item.width).sum();
+        line_top += finish_line(&mut line, line_width, line_top, bounds, layout, &mut placed);
+    }
+
+    placed
+}
+
+/// Height of an inline container that is `width` wide, from the (width, height, baseline)
+/// of its children (see `intrinsic::content_size`). The children are broken into lines like in `arrange`.
+pub fn content_height(layout: &InlineLayout, width: f32, children: &[(f32, f32, f32)])
+-> f32
+{
+    let items = children.iter().map(|&(width, height, baseline)| {
+        InlineItem { node: (), width, height, baseline }
+    }).collect();
+
+    break_lines(items, width).iter().map(|line| line_metrics(line, layout).0).sum()
+}
+
+/// Puts the items on lines, a new line is started when the next item doesn't fit
+/// on the current one. Items that are wider than `width` get a line for themselves.
+fn break_lines<N>(items: Vec<InlineItem<N>>, width: f32)
+-> Vec<Vec<InlineItem<N>>>
+{
+    let mut lines = Vec::new();
+    let mut line = Vec::<InlineItem<N>>::new();
+    let mut line_width = 0.0;
+
+    for item in items {
+        if !line.is_empty() && line_width + item.width > width {
+            lines.push(mem::take(&mut line));
+            line_width = 0.0;
+        }
+        line_width += item.width;
+        line.push(item);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Returns the height of a line and the ascent and descent of its baseline group
+fn line_metrics<N>(line: &[InlineItem<N>], layout: &InlineLayout)
+-> (f32, f32, f32)
+{
+    let min_line_height = layout.line_height.unwrap_or(0.0);
+
+    // for baseline alignment, the line has to fit the tallest part above and below the baseline
+    let ascent = line.iter().map(|item| item.baseline).fold(0.0, f32::max);
+    let descent = line.iter().map(|item| item.height - item.baseline).fold(0.0, f32::max);
+
+    let line_height = match layout.vertical_align {
+        VerticalAlign::Baseline => ascent + descent,
+        _ => line.iter().map(|item| item.height).fold(0.0, f32::max),
+    }.max(min_line_height);
+
+    (line_height, ascent, descent)
+}
+
+/// Positions the items of one line and returns the height of the line
+fn finish_line<N>(line: &mut Vec<InlineItem<N>>, line_width: f32, line_top: f32,
+                         bounds: Bounds, layout: &InlineLayout,
+                         placed: &mut Vec<(N, Bounds)>)
+-> f32
+{
+    let (line_height, ascent, descent) = line_metrics(line, layout);
+
+    // center the baseline group in lines that are taller than their content
+    let baseline_top = line_top + (line_height - (ascent + descent)) * 0.5;
+
+    let free_space = (bounds.width - line_width).max(0.0);
+    let mut left = bounds.left + match layout.text_align {
+        TextAlign::Start => 0.0,
+        TextAlign::Center => free_space * 0.5,
+        TextAlign::End => free_space,
+    };
+
+    for item in line.drain(..) {
+        let top = match layout.vertical_align {
+            VerticalAlign::Top => line_top,
+            VerticalAlign::Middle => line_top + (line_height - item.height) * 0.5,
+            VerticalAlign::Bottom => line_top + line_height - item.height,
+            VerticalAlign::Baseline => baseline_top + ascent - item.baseline,
+        };
+        placed.push((item.node, Bounds::new(left, top, item.width, item.height)));
+        left += item.width;
+    }
+
+    line_height
+}
+
+#[test]
+fn inline_items_wrap_to_next_line() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection, LayoutMode};
+    use intrinsic::{min_content_height, max_content_height};
+
+    let layout = InlineLayout { line_height: Some(20.0), .. InlineLayout::default() };
+    let container = NodeRef::new(NodeData::empty(FlexDirection::Row, 0).with_layout_mode(LayoutMode::Inline(layout)));
+    for &(width, height) in &[(40.0, 10.0), (40.0, 30.0), (40.0, 10.0)] {
+        let mut chip = NodeData::empty(FlexDirection::Row, 0);
+        chip.width = Some(width);
+        chip.height = Some(height);
+        container.append(NodeRef::new(chip));
+    }
+
+    let placed = arrange(&container, Bounds::new(0.0, 0.0, 100.0, 100.0), &layout, &mut MeasureCache::new());
+    let positions = placed.iter().map(|(_, b)| (b.left, b.top)).collect::<Vec<_>>();
+
+    // first line is 30 pixels tall (bottom edges on the baseline), third chip wraps
+    assert_eq!(positions, vec![(0.0, 20.0), (40.0, 0.0), (0.0, 35.0)]);
+
+    // the content height wraps the same way: 30 + 20 at a width of 100, every
+    // chip on its own line for min-content, a single line for max-content
+    assert_eq!(MeasureCache::new().max_content_height_at(&container, 100.0), 50.0);
+    assert_eq!(min_content_height(&container), 20.0 + 30.0 + 20.0);
+    assert_eq!(max_content_height(&container), 30.0);
+
+    // the width of a column is passed down to the container
+    let mut column = NodeData::empty(FlexDirection::Column, 0);
+    column.width = Some(100.0);
+    let column = NodeRef::new(column);
+    column.append(container);
+    assert_eq!(max_content_height(&column), 50.0);
+}
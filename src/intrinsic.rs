@@ -0,0 +1,207 @@
+//! Intrinsic (content-based) sizes of nodes
+//!
+//! Layout modes like tables can't just divide the available space between the
+//! children, they need to know how small a node can get (min-content) and how
+//! much space it would like to have (max-content). There is no text measuring
+//! in this library, so leaf nodes report their content size via the size
+//! constraints: the min-content size of a leaf is its `width` or `min_width`,
+//! the max-content size is its `width` or `max_width`.
+
+use tree_node::TreeNode;
+use node_data::{NodeData, FlexDirection, LayoutMode, InlineLayout};
+use masonry;
+use radial;
+use inline_flow;
+use tree_diagram;
+
+/// Horizontal or vertical axis
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Whether the smallest or the preferred content size is requested
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ContentSize {
+    MinContent,
+    MaxContent,
+}
+
+/// Smallest width the node can get without overflowing its content
+#[inline]
+pub fn min_content_width<T, N: TreeNode<Data = NodeData<T>>>(node: &N)
+-> f32
+{
+    content_size(node, Axis::Horizontal, ContentSize::MinContent)
+}
+
+/// Width the node would like to have if there is enough space
+#[inline]
+pub fn max_content_width<T, N: TreeNode<Data = NodeData<T>>>(node: &N)
+-> f32
+{
+    content_size(node, Axis::Horizontal, ContentSize::MaxContent)
+}
+
+/// Smallest height the node can get without overflowing its content
+#[inline]
+pub fn min_content_height<T, N: TreeNode<Data = NodeData<T>>>(node: &N)
+-> f32
+{
+    content_size(node, Axis::Vertical, ContentSize::MinContent)
+}
+
+/// Height the node would like to have if there is enough space
+#[inline]
+pub fn max_content_height<T, N: TreeNode<Data = NodeData<T>>>(node: &N)
+-> f32
+{
+    content_size(node, Axis::Vertical, ContentSize::MaxContent)
+}
+
+/// Calculates the min-content or max-content size of a node on one axis
+pub fn content_size<T, N: TreeNode<Data = NodeData<T>>>(node: &N, axis: Axis, size: ContentSize)
+-> f32
+{
+    content_size_at(node, axis, size, None)
+}
+
+/// Same as `content_size`, but the node is measured as if it was placed in a
+/// parent that is `available_width` wide. Only heights depend on the available
+/// width (i.e. the number of masonry columns), widths are always intrinsic.
+pub fn content_size_at<T, N: TreeNode<Data = NodeData<T>>>(node: &N, axis: Axis, size: ContentSize, available_width: Option<f32>)
+-> f32
+{
+    content_size_with(node, axis, size, available_width, &mut content_size_at)
+}
+
+/// Same as `content_size_at`, but the sizes of the children are calculated by
+/// `measure_child` (i.e. looked up in a `MeasureCache`), which gets the same
+/// arguments as `content_size_at`
+pub fn content_size_with<T, N, F>(node: &N, axis: Axis, size: ContentSize, available_width: Option<f32>,
+                                  measure_child: &mut F)
+-> f32
+where N: TreeNode<Data = NodeData<T>>, F: FnMut(&N, Axis, ContentSize, Option<f32>) -> f32
+{
+    let (exact, min, max) = constraints(&node.borrow(), axis);
+    let available_width = match axis {
+        Axis::Horizontal => None,
+        Axis::Vertical => node_width(&node.borrow(), available_width),
+    };
+
+    if let Some(exact) = exact {
+        return clamp(exact, min, max);
+    }
+
+    let content = if node.first_child().is_none() {
+        let leaf = match size {
+            ContentSize::MinContent => min,
+            ContentSize::MaxContent => max.or(min),
+        };
+        leaf.unwrap_or(0.0)
+    } else if let LayoutMode::TreeDiagram(ref diagram_layout) = node.borrow().layout_mode {
+        // the descendants of a diagram are sized by their own size only, see `tree_diagram`
+        tree_diagram::content_size(node, diagram_layout, axis)
+    } else if let (LayoutMode::Inline(inline_layout), Axis::Vertical) = (node.borrow().layout_mode, axis) {
+        inline_content_height(node, &inline_layout, size, available_width, measure_child)
+    } else {
+        let child_width = available_width.and_then(|width| children_width(&node.borrow(), width));
+        let children = node.children().map(|child| measure_child(&child, axis, size, child_width)).collect::<Vec<_>>();
+        let data = node.borrow();
+        match data.layout_mode {
+            // the children of a treemap are sized by their weight, not by their content
+            LayoutMode::Treemap(ref treemap_layout) => treemap_layout.padding * 2.0,
+            LayoutMode::Masonry(ref masonry_layout) => masonry::content_size(masonry_layout, axis, size,
+                                                                             available_width, &children),
+            LayoutMode::Radial(ref radial_layout) => radial::content_size(radial_layout, &children),
+            _ if children_flow_along(&data, axis, size) => children.iter().sum(),
+            _ => children.iter().cloned().fold(0.0, f32::max),
+        }
+    };
+
+    clamp(content, min, max)
+}
+
+/// Returns (exact, min, max) size of the node on the given axis
+fn constraints<T>(data: &NodeData<T>, axis: Axis)
+-> (Option<f32>, Option<f32>, Option<f32>)
+{
+    match axis {
+        Axis::Horizontal => (data.width, data.min_width, data.max_width),
+        Axis::Vertical => (data.height, data.min_height, data.max_height),
+    }
+}
+
+/// Height of an inline container: the children are broken into lines at the
+/// width of the container. If the width isn't known, every child is on its own
+/// line for the min-content height and all children are on one line for the
+/// max-content height. Like in `inline_flow::arrange`, the children always get
+/// their max-content size.
+fn inline_content_height<T, N, F>(node: &N, layout: &InlineLayout, size: ContentSize, width: Option<f32>,
+                                  measure_child: &mut F)
+-> f32
+where N: TreeNode<Data = NodeData<T>>, F: FnMut(&N, Axis, ContentSize, Option<f32>) -> f32
+{
+    let children = node.children().map(|child| {
+        let width = measure_child(&child, Axis::Horizontal, ContentSize::MaxContent, None);
+        let height = measure_child(&child, Axis::Vertical, ContentSize::MaxContent, Some(width));
+        (width, height, child.borrow().baseline.unwrap_or(height))
+    }).collect::<Vec<_>>();
+
+    let width = width.unwrap_or(match size {
+        ContentSize::MinContent => 0.0,
+        ContentSize::MaxContent => f32::INFINITY,
+    });
+    inline_flow::content_height(layout, width, &children)
+}
+
+/// Width of the node in a parent that is `available_width` wide: its own `width`,
+/// or the available width (children of columns are as wide as the column)
+fn node_width<T>(data: &NodeData<T>, available_width: Option<f32>)
+-> Option<f32>
+{
+    data.width.or(available_width).map(|width| clamp(width, data.min_width, data.max_width))
+}
+
+/// Width that the children of a node with the given width are placed with,
+/// `None` if it depends on the content of the children (i.e. in a row)
+fn children_width<T>(data: &NodeData<T>, width: f32)
+-> Option<f32>
+{
+    match data.layout_mode {
+        LayoutMode::Masonry(ref masonry_layout) => Some(masonry::column_width(width, masonry_layout)),
+        LayoutMode::Table => Some(width),
+        LayoutMode::Flex | LayoutMode::TableCell { .. } if data.flex_direction == FlexDirection::Column => Some(width),
+        _ => None,
+    }
+}
+
+/// Returns if the children of the node are placed one after another on the axis
+/// (their sizes add up) or next to each other (the largest child wins)
+fn children_flow_along<T>(data: &NodeData<T>, axis: Axis, size: ContentSize)
+-> bool
+{
+    match (data.layout_mode, axis) {
+        // inline content at max-content width is a single line,
+        // at min-content width every child is on its own line
+        (LayoutMode::Inline(_), Axis::Horizontal) => size == ContentSize::MaxContent,
+        // the height of inline content is calculated with `inline_content_height`
+        (LayoutMode::Inline(_), Axis::Vertical) => false,
+        (LayoutMode::Table, axis) => axis == Axis::Vertical,
+        (LayoutMode::TableRow, axis) => axis == Axis::Horizontal,
+        (_, Axis::Horizontal) => data.flex_direction == FlexDirection::Row,
+        (_, Axis::Vertical) => data.flex_direction == FlexDirection::Column,
+    }
+}
+
+/// Applies max, then min constraints, in the same order as the layout does
+#[inline]
+fn clamp(value: f32, min: Option<f32>, max: Option<f32>)
+-> f32
+{
+    let mut value = value;
+    if let Some(max) = max { if value > max { value = max; } }
+    if let Some(min) = min { if value < min { value = min; } }
+    value
+}
@@ -0,0 +1,156 @@
+//! Label placement with collision avoidance (for map / print style output)
+//!
+//! Every label has an anchor point and a list of candidate positions around
+//! it. Labels are placed greedily in the order of their priority: each label
+//! gets the first candidate position that doesn't overlap any obstacle or
+//! label placed before it. Labels that can't be placed without overlapping
+//! are dropped - since the most important labels are placed first, the labels
+//! that are dropped are always the least important ones.
+
+use node_data::NodeData;
+use rect::Rect;
+
+/// Position of a label relative to its anchor point (compass directions, north is up)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LabelPosition {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+    /// Centered on the anchor
+    Center,
+}
+
+impl LabelPosition {
+
+    /// The usual cartographic order of preference: top right first
+    pub fn all()
+    -> Vec<LabelPosition>
+    {
+        use self::LabelPosition::*;
+        vec![NE, E, SE, N, S, NW, W, SW]
+    }
+
+    /// Top left corner of a `width` x `height` label at this position,
+    /// `gap` is the distance between anchor and label
+    fn top_left(&self, anchor: (f32, f32), width: f32, height: f32, gap: f32)
+    -> (f32, f32)
+    {
+        use self::LabelPosition::*;
+        let (x, y) = anchor;
+
+        let left = match *self {
+            N | S | Center => x - width * 0.5,
+            NE | E | SE => x + gap,
+            NW | W | SW => x - gap - width,
+        };
+
+        let top = match *self {
+            E | W | Center => y - height * 0.5,
+            N | NE | NW => y - gap - height,
+            S | SE | SW => y + gap,
+        };
+
+        (left, top)
+    }
+}
+
+/// A label that should be placed
+#[derive(Debug, Clone)]
+pub struct Label<T> {
+    /// Point the label belongs to
+    pub anchor: (f32, f32),
+    /// Width of the label
+    pub width: f32,
+    /// Height of the label
+    pub height: f32,
+    /// Rotation of the label around its anchor in degrees (i.e. for street names)
+    pub angle: f32,
+    /// Labels with a higher priority are placed first
+    pub priority: f32,
+    /// Candidate positions, in order of preference
+    pub candidates: Vec<LabelPosition>,
+    /// Data of the label, ends up in the placed rectangle
+    pub data: NodeData<T>,
+}
+
+/// A label that was placed successfully
+#[derive(Debug, Clone)]
+pub struct PlacedLabel<T> {
+    /// Which of the candidates was chosen
+    pub position: LabelPosition,
+    /// Where the label ended up (rotated if the label has an angle)
+    pub rect: Rect<T>,
+}
+
+/// Places the labels, avoiding each other and the `obstacles`. Returns one
+/// entry per label, in the same order as `labels`, `None` if the label was dropped.
+/// `gap` is the distance between a label and its anchor.
+pub fn place_labels<T: Clone>(labels: &[Label<T>], obstacles: &[Rect<T>], gap: f32)
+-> Vec<Option<PlacedLabel<T>>>
+{
+    let mut order = (0..labels.len()).collect::<Vec<_>>();
+    // highest priority first, NaN priorities from user data are placed last
+    let priority = |index: usize| if labels[index].priority.is_nan() { f32::NEG_INFINITY } else { labels[index].priority };
+    order.sort_by(|&a, &b| priority(b).total_cmp(&priority(a)));
+
+    let mut placed = vec![None; labels.len()];
+    let mut taken = Vec::<Rect<T>>::new();
+
+    for index in order {
+        let label = &labels[index];
+
+        let candidate = label.candidates.iter().map(|position| {
+            let (left, top) = position.top_left(label.anchor, label.width, label.height, gap);
+            let mut rect = Rect::new_wh(left, top, label.width, label.height, 0.0, label.data.clone());
+            if label.angle != 0.0 {
+                rect.rotate_around(label.anchor.0, label.anchor.1, label.angle);
+            }
+            (*position, rect)
+        }).find(|(_, rect)| {
+            !obstacles.iter().chain(taken.iter()).any(|other| rect.overlaps(other))
+        });
+
+        if let Some((position, rect)) = candidate {
+            taken.push(rect.clone());
+            placed[index] = Some(PlacedLabel { position, rect });
+        }
+    }
+
+    placed
+}
+
+#[test]
+fn lower_priority_labels_move_or_get_dropped() {
+    use node_data::FlexDirection;
+
+    let label = |priority: f32, candidates: Vec<LabelPosition>| Label {
+        anchor: (50.0, 50.0),
+        width: 20.0,
+        height: 10.0,
+        angle: 0.0,
+        priority,
+        candidates,
+        data: NodeData::empty(FlexDirection::Row, ()),
+    };
+
+    // the obstacle blocks all positions to the right of the anchor
+    let obstacles = vec![Rect::new_wh(61.0, 0.0, 100.0, 100.0, 0.0, NodeData::empty(FlexDirection::Row, ()))];
+    let labels = vec![
+        label(1.0, vec![LabelPosition::N]),
+        label(2.0, vec![LabelPosition::NE, LabelPosition::N]),
+        label(0.0, LabelPosition::all()),
+        label(f32::NAN, vec![LabelPosition::N]),
+    ];
+
+    let placed = place_labels(&labels, &obstacles, 2.0);
+    assert_eq!(placed[1].as_ref().map(|p| p.position), Some(LabelPosition::N));
+    assert!(placed[0].is_none());
+    assert_eq!(placed[2].as_ref().map(|p| p.position), Some(LabelPosition::S));
+    // a NaN priority is the lowest one, the position is already taken
+    assert!(placed[3].is_none());
+}
@@ -0,0 +1,250 @@
+//! Computed layout of the nodes, stored separately from their `NodeData`
+//!
+//! The layout only reads the tree and writes its results into a `LayoutResults`,
+//! so laying out the same tree twice gives the same result and rotations or
+//! transforms applied to the output never leak back into the input.
+
+use std::collections::HashMap;
+use tree_node::{HasNodeId, TreeNode};
+use node_data::{NodeData, LayoutMode};
+use rect::{Rect, Bounds, corners_bounding_box, corners_contain_point};
+use transform::Transform2D;
+
+/// Computed layout of a single node
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayoutResult {
+    /// Position and size of the node, before scrolling, rotations and transforms
+    pub bounds: Bounds,
+    /// Area covered by the node and all of its descendants (same coordinates as
+    /// `bounds`) - larger than `bounds` if the children overflow the node
+    pub content_box: Bounds,
+    /// Z-index of the node
+    pub z: f32,
+    /// Rotation (in degrees) around the center of `bounds` that was applied by
+    /// the parent of the node (i.e. a radial layout with `rotate_children`)
+    pub rotation: f32,
+    /// x coordinates of the final corners on the screen (tl, tr, bl, br)
+    pub x: [f32; 4],
+    /// y coordinates of the final corners on the screen (tl, tr, bl, br)
+    pub y: [f32; 4],
+    /// Maps from the local space of the node (the top left corner of `bounds` is (0, 0))
+    /// to screen space
+    pub to_screen: Transform2D,
+}
+
+impl LayoutResult {
+
+    /// Creates the result for a node at `bounds` that is not rotated or transformed
+    #[inline]
+    pub fn new(bounds: Bounds, z: f32)
+    -> Self
+    {
+        Self {
+            bounds,
+            content_box: bounds,
+            z,
+            rotation: 0.0,
+            x: [bounds.left, bounds.right(), bounds.left, bounds.right()],
+            y: [bounds.top, bounds.top, bounds.bottom(), bounds.bottom()],
+            to_screen: Transform2D::translation(bounds.left, bounds.top),
+        }
+    }
+
+    /// Moves the node as part of the layout (i.e. baseline alignment), the layout box moves too
+    pub fn translate(&mut self, x: f32, y: f32)
+    {
+        self.bounds.left += x;
+        self.bounds.top += y;
+        self.content_box.left += x;
+        self.content_box.top += y;
+        for i in 0..4 {
+            self.x[i] += x;
+            self.y[i] += y;
+        }
+        self.to_screen = self.to_screen.then(&Transform2D::translation(x, y));
+    }
+
+    /// Places the layout box on the screen: `transform` is the combined transform of
+    /// the node and its ancestors (scrolling, rotation, `NodeData::transform`)
+    pub fn set_screen_transform(&mut self, transform: &Transform2D)
+    {
+        let bounds = self.bounds;
+        let corners = [(bounds.left, bounds.top), (bounds.right(), bounds.top),
+                       (bounds.left, bounds.bottom()), (bounds.right(), bounds.bottom())];
+        for (i, &(x, y)) in corners.iter().enumerate() {
+            let (x, y) = transform.transform_point(x, y);
+            self.x[i] = x;
+            self.y[i] = y;
+        }
+        self.to_screen = Transform2D::translation(bounds.left, bounds.top).then(transform);
+    }
+
+    /// Axis-aligned bounding box of the node on the screen
+    #[inline]
+    pub fn bounding_box(&self)
+    -> Bounds
+    {
+        corners_bounding_box(&self.x, &self.y)
+    }
+
+    /// Area of the node on the screen (also correct after rotations and skews)
+    #[inline]
+    pub fn area(&self)
+    -> f32
+    {
+        // the corners form a parallelogram, spanned by the top and the left edge
+        ((self.x[1] - self.x[0]) * (self.y[2] - self.y[0]) - (self.y[1] - self.y[0]) * (self.x[2] - self.x[0])).abs()
+    }
+
+    /// Tests if a point is inside of the node on the screen (edges are inside)
+    #[inline]
+    pub fn contains_point(&self, x: f32, y: f32)
+    -> bool
+    {
+        corners_contain_point(&self.x, &self.y, x, y)
+    }
+
+    /// Creates the output rectangle for the renderer
+    #[inline]
+    pub fn to_rect<T>(&self, data: NodeData<T>)
+    -> Rect<T>
+    {
+        Rect { x: self.x, y: self.y, z: self.z, data, to_screen: self.to_screen }
+    }
+}
+
+/// Output of the layout for a single node that refers to the node instead of
+/// copying its data, see `UiScreen::into_records`
+#[derive(Debug, Clone)]
+pub struct LayoutRecord<H> {
+    /// The node (a `NodeRef` or the `NodeId` of an arena node), the user data is in `NodeData::data`
+    pub node: H,
+    /// Computed layout of the node
+    pub layout: LayoutResult,
+}
+
+/// Everything the layout of a subtree depends on apart from the nodes of the
+/// subtree - if the key of a clean node didn't change since the last layout,
+/// its subtree is reused instead of being laid out again
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayoutKey {
+    pub width: f32,
+    pub height: f32,
+    pub z: f32,
+    pub max_z: f32,
+    /// Layout mode of the parent, `None` for the root
+    pub parent_layout_mode: Option<LayoutMode>,
+}
+
+/// The computed layout of every node of a tree, see `UiScreen::layout`
+#[derive(Debug, Clone, Default)]
+pub struct LayoutResults {
+    /// `NodeRef::node_id` -> result
+    results: HashMap<u64, LayoutResult>,
+    /// `NodeRef::node_id` -> key the node was laid out with
+    keys: HashMap<u64, LayoutKey>,
+}
+
+impl LayoutResults {
+
+    /// Creates an empty set of results
+    #[inline]
+    pub fn new()
+    -> Self
+    {
+        Self::default()
+    }
+
+    /// Result of the node, `None` if the node wasn't laid out
+    #[inline]
+    pub fn get<K: HasNodeId>(&self, node: &K)
+    -> Option<&LayoutResult>
+    {
+        self.results.get(&node.node_id())
+    }
+
+    /// Mutable result of the node, `None` if the node wasn't laid out
+    #[inline]
+    pub fn get_mut<K: HasNodeId>(&mut self, node: &K)
+    -> Option<&mut LayoutResult>
+    {
+        self.results.get_mut(&node.node_id())
+    }
+
+    /// Stores the result of a node, replacing the previous result
+    #[inline]
+    pub fn insert<K: HasNodeId>(&mut self, node: &K, result: LayoutResult)
+    {
+        self.results.insert(node.node_id(), result);
+    }
+
+    /// Key the node was laid out with, `None` if the node wasn't laid out
+    #[inline]
+    pub fn key<K: HasNodeId>(&self, node: &K)
+    -> Option<&LayoutKey>
+    {
+        self.keys.get(&node.node_id())
+    }
+
+    /// Stores the key the node was laid out with
+    #[inline]
+    pub fn insert_key<K: HasNodeId>(&mut self, node: &K, key: LayoutKey)
+    {
+        self.keys.insert(node.node_id(), key);
+    }
+
+    /// Moves the results and keys of the node and all of its descendants to `target`
+    pub fn move_subtree<N: TreeNode>(&mut self, node: &N, target: &mut LayoutResults)
+    {
+        for descendant in node.descendants() {
+            let id = descendant.node_id();
+            if let Some(result) = self.results.remove(&id) {
+                target.results.insert(id, result);
+            }
+            if let Some(key) = self.keys.remove(&id) {
+                target.keys.insert(id, key);
+            }
+        }
+    }
+
+    /// Moves all results and keys of `other` into these results
+    pub fn append(&mut self, other: LayoutResults)
+    {
+        self.results.extend(other.results);
+        self.keys.extend(other.keys);
+    }
+
+    /// Number of nodes with a result
+    #[inline]
+    pub fn len(&self)
+    -> usize
+    {
+        self.results.len()
+    }
+
+    /// Returns if no node has a result
+    #[inline]
+    pub fn is_empty(&self)
+    -> bool
+    {
+        self.results.is_empty()
+    }
+
+    /// Removes all results
+    #[inline]
+    pub fn clear(&mut self)
+    {
+        self.results.clear();
+        self.keys.clear();
+    }
+
+    /// Moves the node and all of its descendants as part of the layout
+    pub fn translate_subtree<N: TreeNode>(&mut self, node: &N, x: f32, y: f32)
+    {
+        for descendant in node.descendants() {
+            if let Some(result) = self.get_mut(&descendant) {
+                result.translate(x, y);
+            }
+        }
+    }
+}
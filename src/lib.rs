@@ -4,6 +4,12 @@
 //! store in the UI. You can for example, have a function callback in the UI.
 //!
 //! This library doesn't do any drawing, only layout.
+//!
+//! The default tree (`NodeRef`) is reference counted and can only be used on one
+//! thread. A `UiScreen` built on an `ArenaTree` (see `UiScreen::new_in_arena`)
+//! is `Send` and `Sync`, so the layout can be computed on a worker thread, and
+//! large independent subtrees can be laid out on multiple threads
+//! (see `UiScreen::compute_layout_parallel`).
 
 #[cfg(feature = "use_simd")]
 pub extern crate simd;
@@ -11,12 +17,43 @@ pub extern crate simd;
 // Forked from https://github.com/SimonSapin/rust-forest/blob/master/rctree/lib.rs
 // because the crate is not available anymore on crates.io. MIT License Simon Sapin
 pub mod rctree;
+pub mod tree_node;
+pub mod arena;
 pub mod rect;
 pub mod ui_screen;
 pub mod node_data;
+pub mod shared_size;
+pub mod baseline;
+pub mod intrinsic;
+pub mod table;
+pub mod inline_flow;
+pub mod masonry;
+pub mod tree_diagram;
+pub mod treemap;
+pub mod radial;
+pub mod packing;
+pub mod label_placement;
+pub mod popover;
+pub mod transform;
+pub mod oriented_rect;
+pub mod hit_test;
+pub mod spatial_index;
+pub mod coordinates;
+pub mod damage;
+pub mod layout_result;
+pub mod measure_cache;
 
 pub use rctree::NodeRef as NodeRef;
-pub use rect::Rect;
-pub use ui_screen::UiScreen;
-pub use node_data::{NodeData, FlexDirection};
+pub use tree_node::{HasNodeId, TreeNode, LayoutTree};
+pub use arena::{Arena, ArenaTree, NodeId};
+pub use rect::{Rect, Bounds};
+pub use transform::Transform2D;
+pub use oriented_rect::OrientedRect;
+pub use spatial_index::SpatialIndex;
+pub use layout_result::{LayoutResult, LayoutResults, LayoutRecord};
+pub use measure_cache::MeasureCache;
+pub use ui_screen::{UiScreen, ParallelLayout};
+pub use node_data::{NodeData, FlexDirection, AlignItems, LayoutMode, Overflow,
+                    InlineLayout, TextAlign, VerticalAlign, MasonryLayout, MasonryColumns,
+                    TreemapLayout, RadialLayout, TreeDiagramLayout, TreeOrientation};
 
@@ -0,0 +1,144 @@
+//! Masonry (waterfall) layout: children are distributed into columns, every
+//! child is appended to the column that is currently the shortest
+
+use tree_node::TreeNode;
+use node_data::{NodeData, MasonryLayout, MasonryColumns};
+use rect::Bounds;
+use measure_cache::MeasureCache;
+use intrinsic::{Axis, ContentSize};
+
+/// Calculates the position of the children of a masonry container inside of `bounds`.
+/// Children are as wide as a column, the height is the max-content height of the child.
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(container: &N, bounds: Bounds, layout: &MasonryLayout,
+                                                   cache: &mut MeasureCache)
+-> Vec<(N, Bounds)>
+{
+    let column_count = column_count(bounds.width, layout);
+    let column_width = column_width(bounds.width, layout);
+    let mut column_heights = vec![0.0_f32; used_columns(column_count, container.children().count())];
+
+    container.children().map(|node| {
+        let (column, column_height) = shortest_column(&column_heights);
+        let height = cache.max_content_height_at(&node, column_width);
+        let left = bounds.left + column as f32 * (column_width + layout.gap);
+        let top = bounds.top + column_height;
+
+        column_heights[column] += height + layout.gap;
+        (node, Bounds::new(left, top, column_width, height))
+    }).collect()
+}
+
+/// Number of columns that the available width is divided into, at least one
+fn column_count(available_width: f32, layout: &MasonryLayout)
+-> usize
+{
+    match layout.columns {
+        MasonryColumns::Count(count) => count.max(1) as usize,
+        MasonryColumns::MinWidth(min_width) if min_width > 0.0 => {
+            (((available_width + layout.gap) / (min_width + layout.gap)).floor() as usize).max(1)
+        },
+        MasonryColumns::MinWidth(_) => 1,
+    }
+}
+
+/// Width of each column when the available width is divided into columns
+pub fn column_width(available_width: f32, layout: &MasonryLayout)
+-> f32
+{
+    let column_count = column_count(available_width, layout);
+    ((available_width - layout.gap * (column_count - 1) as f32) / column_count as f32).max(0.0)
+}
+
+/// Number of columns that can contain a child: every child starts a new column
+/// as long as there are empty ones, so there are never more than one per child.
+/// Only these columns are allocated, the count itself can be huge (i.e. `u32::MAX`).
+fn used_columns(column_count: usize, child_count: usize)
+-> usize
+{
+    column_count.min(child_count).max(1)
+}
+
+/// Min-content or max-content size of a masonry container on one axis, from
+/// the content sizes of its children on the same axis (see `intrinsic::content_size`).
+/// If the width of the container is known, its height is measured with the columns of that width.
+pub fn content_size(layout: &MasonryLayout, axis: Axis, size: ContentSize, width: Option<f32>, children: &[f32])
+-> f32
+{
+    let column_count = match (layout.columns, axis, width) {
+        (MasonryColumns::Count(count), ..) => count.max(1) as usize,
+        (_, Axis::Vertical, Some(width)) => column_count(width, layout),
+        // the container gets narrow (and tall) with a single column,
+        // wide (and short) with every child in its own column
+        (MasonryColumns::MinWidth(_), ..) => match (axis, size) {
+            (Axis::Horizontal, ContentSize::MinContent) | (Axis::Vertical, ContentSize::MaxContent) => 1,
+            _ => children.len().max(1),
+        },
+    };
+
+    match axis {
+        Axis::Horizontal => {
+            let min_column_width = match layout.columns {
+                MasonryColumns::MinWidth(min_width) => min_width.max(0.0),
+                MasonryColumns::Count(_) => 0.0,
+            };
+            let column_width = children.iter().cloned().fold(min_column_width, f32::max);
+            column_width * column_count as f32 + layout.gap * (column_count - 1) as f32
+        },
+        Axis::Vertical => {
+            let mut column_heights = vec![0.0_f32; used_columns(column_count, children.len())];
+            for height in children {
+                let (column, _) = shortest_column(&column_heights);
+                column_heights[column] += height + layout.gap;
+            }
+            // there is no gap after the last child of a column
+            column_heights.iter().map(|height| (height - layout.gap).max(0.0)).fold(0.0, f32::max)
+        },
+    }
+}
+
+/// Index and height of the shortest column, the leftmost column wins if two columns have the same height
+fn shortest_column(column_heights: &[f32])
+-> (usize, f32)
+{
+    column_heights.iter().cloned().enumerate()
+        .fold((0, f32::MAX), |min, cur| if cur.1 < min.1 { cur } else { min })
+}
+
+#[test]
+fn children_are_appended_to_the_shortest_column() {
+    use rctree::NodeRef;
+    use node_data::{FlexDirection, LayoutMode};
+    use intrinsic::{max_content_width, max_content_height, min_content_height};
+
+    let layout = MasonryLayout { columns: MasonryColumns::Count(2), gap: 10.0 };
+    let container = NodeRef::new(NodeData::empty(FlexDirection::Column, 0).with_layout_mode(LayoutMode::Masonry(layout)));
+    for (index, height) in [50.0, 20.0, 10.0, 40.0].iter().enumerate() {
+        let mut child = NodeData::empty(FlexDirection::Row, index + 1);
+        child.width = Some(30.0);
+        child.height = Some(*height);
+        container.append(NodeRef::new(child));
+    }
+
+    let children = arrange(&container, Bounds::new(0.0, 0.0, 110.0, 200.0), &layout, &mut MeasureCache::new());
+    let bounds = children.iter().map(|child| child.1).collect::<Vec<_>>();
+
+    // two columns of (110 - 10) / 2, the third child goes below the second one,
+    // the right column is still shorter for the fourth child (20 + 10 + 10 + 10 < 50 + 10)
+    assert_eq!(bounds[0], Bounds::new(0.0, 0.0, 50.0, 50.0));
+    assert_eq!(bounds[1], Bounds::new(60.0, 0.0, 50.0, 20.0));
+    assert_eq!(bounds[2], Bounds::new(60.0, 30.0, 50.0, 10.0));
+    assert_eq!(bounds[3], Bounds::new(60.0, 50.0, 50.0, 40.0));
+
+    // two columns of the widest child, the right column is the taller one
+    assert_eq!(max_content_width(&container), 30.0 * 2.0 + 10.0);
+    assert_eq!(max_content_height(&container), 20.0 + 10.0 + 10.0 + 10.0 + 40.0);
+
+    // huge column counts only allocate a column per child
+    for &columns in &[MasonryColumns::Count(u32::MAX), MasonryColumns::MinWidth(1.0e-30)] {
+        let layout = MasonryLayout { columns, gap: 0.0 };
+        container.borrow_mut().layout_mode = LayoutMode::Masonry(layout);
+        let children = arrange(&container, Bounds::new(0.0, 0.0, 110.0, 200.0), &layout, &mut MeasureCache::new());
+        assert!(children.iter().all(|child| child.1.top == 0.0));
+        assert_eq!(min_content_height(&container), 50.0);
+    }
+}
@@ -0,0 +1,300 @@
+//! Cache for the measured sizes of nodes
+//!
+//! Layout modes like tables, inline flow or masonry measure the content size of
+//! their children before they arrange them, and nested containers measure the
+//! same subtree again on every level. The cache stores every measurement of a
+//! node, keyed by the axis, the sizing mode and the available width, so that
+//! each node is only measured once per key. Heights can depend on the width
+//! that the node is placed with (i.e. the number of masonry columns), but
+//! widths never depend on the height, so the available height is not part of the key.
+//!
+//! The measured size of a node only depends on its subtree, so the entries of a
+//! node stay valid until the node becomes dirty (see `NodeRef::is_dirty`). The
+//! arranged results of a node are cached separately, see `layout_result::LayoutKey`.
+
+use std::collections::{HashMap, HashSet};
+use tree_node::{HasNodeId, TreeNode};
+use node_data::NodeData;
+use intrinsic::{Axis, ContentSize, content_size_with};
+
+/// What a node is measured for
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeasureKey {
+    /// Axis that is measured
+    pub axis: Axis,
+    /// Sizing mode: smallest or preferred size
+    pub size: ContentSize,
+    /// Width of the parent the node is measured in, `None` if the width is unconstrained.
+    /// Always `None` for widths, see `MeasureKey::new`.
+    pub available_width: Option<f32>,
+}
+
+impl MeasureKey {
+
+    /// Key for a size on the axis, measured with the available width.
+    /// Widths don't depend on the available width, so it is dropped for the
+    /// horizontal axis (and all widths share one entry).
+    #[inline]
+    pub fn new(axis: Axis, size: ContentSize, available_width: Option<f32>)
+    -> Self
+    {
+        let available_width = match axis {
+            Axis::Horizontal => None,
+            Axis::Vertical => available_width,
+        };
+        Self { axis, size, available_width }
+    }
+
+    /// Key for an intrinsic size on the axis, with an unconstrained width
+    #[inline]
+    pub fn intrinsic(axis: Axis, size: ContentSize)
+    -> Self
+    {
+        Self::new(axis, size, None)
+    }
+}
+
+/// Measured sizes of nodes, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct MeasureCache {
+    /// `NodeRef::node_id` -> measurements of the node. There are only a few
+    /// different keys per node, so a linear search is fast enough.
+    entries: HashMap<u64, Vec<(MeasureKey, f32)>>,
+}
+
+impl MeasureCache {
+
+    /// Creates an empty cache
+    #[inline]
+    pub fn new()
+    -> Self
+    {
+        Self::default()
+    }
+
+    /// Cached size of the node, `None` if it wasn't measured for this key
+    #[inline]
+    pub fn get<K: HasNodeId>(&self, node: &K, key: &MeasureKey)
+    -> Option<f32>
+    {
+        self.entries.get(&node.node_id())
+            .and_then(|entries| entries.iter().find(|entry| entry.0 == *key))
+            .map(|entry| entry.1)
+    }
+
+    /// Stores the measured size of the node, replacing the size measured for the same key
+    pub fn insert<K: HasNodeId>(&mut self, node: &K, key: MeasureKey, value: f32)
+    {
+        let entries = self.entries.entry(node.node_id()).or_default();
+        match entries.iter_mut().find(|entry| entry.0 == key) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key, value)),
+        }
+    }
+
+    /// Returns the cached size of the node, or measures the node with `measure` and caches the result
+    pub fn get_or_measure<K: HasNodeId, F>(&mut self, node: &K, key: MeasureKey, measure: F)
+    -> f32
+    where F: FnOnce(&mut Self) -> f32
+    {
+        if let Some(value) = self.get(node, &key) {
+            return value;
+        }
+        let value = measure(self);
+        self.insert(node, key, value);
+        value
+    }
+
+    /// Cached version of `intrinsic::content_size`, the sizes of all
+    /// descendants of the node are cached too
+    #[inline]
+    pub fn content_size<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N, axis: Axis, size: ContentSize)
+    -> f32
+    {
+        self.content_size_at(node, axis, size, None)
+    }
+
+    /// Cached version of `intrinsic::content_size_at`
+    pub fn content_size_at<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N, axis: Axis, size: ContentSize,
+                                                             available_width: Option<f32>)
+    -> f32
+    {
+        self.get_or_measure(node, MeasureKey::new(axis, size, available_width), |cache| {
+            content_size_with(node, axis, size, available_width,
+                              &mut |child, axis, size, child_width| cache.content_size_at(child, axis, size, child_width))
+        })
+    }
+
+    /// Cached version of `intrinsic::min_content_width`
+    #[inline]
+    pub fn min_content_width<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N)
+    -> f32
+    {
+        self.content_size(node, Axis::Horizontal, ContentSize::MinContent)
+    }
+
+    /// Cached version of `intrinsic::max_content_width`
+    #[inline]
+    pub fn max_content_width<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N)
+    -> f32
+    {
+        self.content_size(node, Axis::Horizontal, ContentSize::MaxContent)
+    }
+
+    /// Cached version of `intrinsic::min_content_height`
+    #[inline]
+    pub fn min_content_height<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N)
+    -> f32
+    {
+        self.content_size(node, Axis::Vertical, ContentSize::MinContent)
+    }
+
+    /// Cached version of `intrinsic::max_content_height`
+    #[inline]
+    pub fn max_content_height<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N)
+    -> f32
+    {
+        self.content_size(node, Axis::Vertical, ContentSize::MaxContent)
+    }
+
+    /// Min-content height of the node if it is placed with the given width
+    #[inline]
+    pub fn min_content_height_at<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N, width: f32)
+    -> f32
+    {
+        self.content_size_at(node, Axis::Vertical, ContentSize::MinContent, Some(width))
+    }
+
+    /// Max-content height of the node if it is placed with the given width
+    #[inline]
+    pub fn max_content_height_at<T, N: TreeNode<Data = NodeData<T>>>(&mut self, node: &N, width: f32)
+    -> f32
+    {
+        self.content_size_at(node, Axis::Vertical, ContentSize::MaxContent, Some(width))
+    }
+
+    /// Removes the entries of all dirty nodes below `root` (including the root).
+    /// Call this before the tree is laid out, while the dirty flags are still set.
+    pub fn remove_dirty<N: TreeNode>(&mut self, root: &N)
+    {
+        if !root.is_dirty() {
+            return;
+        }
+        self.entries.remove(&root.node_id());
+        for child in root.children() {
+            self.remove_dirty(&child);
+        }
+    }
+
+    /// Removes the entries of all nodes that are not below `root` (i.e. nodes
+    /// that were removed from the tree), so that the cache doesn't grow forever
+    pub fn remove_detached<N: TreeNode>(&mut self, root: &N)
+    {
+        let ids = root.descendants().map(|node| node.node_id()).collect::<HashSet<_>>();
+        self.entries.retain(|id, _| ids.contains(id));
+    }
+
+    /// Moves the entries of the node and all of its descendants to `target`
+    pub fn move_subtree<N: TreeNode>(&mut self, node: &N, target: &mut MeasureCache)
+    {
+        for descendant in node.descendants() {
+            let id = descendant.node_id();
+            if let Some(entries) = self.entries.remove(&id) {
+                target.entries.insert(id, entries);
+            }
+        }
+    }
+
+    /// Moves all entries of `other` into this cache
+    pub fn append(&mut self, other: MeasureCache)
+    {
+        self.entries.extend(other.entries);
+    }
+
+    /// Number of nodes with cached sizes
+    #[inline]
+    pub fn len(&self)
+    -> usize
+    {
+        self.entries.len()
+    }
+
+    /// Returns if no sizes are cached
+    #[inline]
+    pub fn is_empty(&self)
+    -> bool
+    {
+        self.entries.is_empty()
+    }
+
+    /// Removes all cached sizes
+    #[inline]
+    pub fn clear(&mut self)
+    {
+        self.entries.clear();
+    }
+}
+
+#[test]
+fn cached_sizes_are_invalidated_by_changes() {
+    use rctree::NodeRef;
+    use node_data::FlexDirection;
+    use intrinsic::max_content_width;
+
+    let row = NodeRef::new(NodeData::empty(FlexDirection::Row, 0));
+    let mut fixed = NodeData::empty(FlexDirection::Row, 0);
+    fixed.width = Some(20.0);
+    let first = NodeRef::new(fixed.clone());
+    row.append(first.clone());
+    row.append(NodeRef::new(fixed));
+
+    let mut cache = MeasureCache::new();
+    assert_eq!(cache.max_content_width(&row), 40.0);
+    // the children were measured (and cached) as part of the row
+    assert_eq!(cache.get(&first, &MeasureKey::intrinsic(Axis::Horizontal, ContentSize::MaxContent)), Some(20.0));
+    assert_eq!(cache.len(), 3);
+    row.mark_clean();
+
+    first.borrow_mut().width = Some(30.0);
+    assert_eq!(cache.max_content_width(&row), 40.0);
+    cache.remove_dirty(&row);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.max_content_width(&row), max_content_width(&row));
+    assert_eq!(cache.max_content_width(&row), 50.0);
+}
+
+#[test]
+fn heights_are_cached_per_available_width() {
+    use rctree::NodeRef;
+    use node_data::{FlexDirection, LayoutMode, MasonryLayout, MasonryColumns};
+
+    let layout = MasonryLayout { columns: MasonryColumns::MinWidth(50.0), gap: 0.0 };
+    let masonry = NodeRef::new(NodeData::empty(FlexDirection::Column, 0).with_layout_mode(LayoutMode::Masonry(layout)));
+    let mut card = NodeData::empty(FlexDirection::Row, 0);
+    card.height = Some(10.0);
+    let first = NodeRef::new(card.clone());
+    masonry.append(first.clone());
+    for _ in 0..3 {
+        masonry.append(NodeRef::new(card.clone()));
+    }
+
+    // without a width, a single column is as tall as it gets, 100 pixels have room for two columns
+    let mut cache = MeasureCache::new();
+    assert_eq!(cache.max_content_height(&masonry), 40.0);
+    assert_eq!(cache.max_content_height_at(&masonry, 100.0), 20.0);
+    assert_eq!(cache.get(&masonry, &MeasureKey::new(Axis::Vertical, ContentSize::MaxContent, Some(100.0))), Some(20.0));
+    assert_eq!(cache.get(&masonry, &MeasureKey::intrinsic(Axis::Vertical, ContentSize::MaxContent)), Some(40.0));
+    // the children were measured at the width of a column
+    assert_eq!(cache.get(&first, &MeasureKey::new(Axis::Vertical, ContentSize::MaxContent, Some(50.0))), Some(10.0));
+
+    // widths don't depend on the available width
+    assert_eq!(MeasureKey::new(Axis::Horizontal, ContentSize::MaxContent, Some(100.0)),
+               MeasureKey::intrinsic(Axis::Horizontal, ContentSize::MaxContent));
+
+    // a column passes its own width down to its children
+    let mut column = NodeData::empty(FlexDirection::Column, 0);
+    column.width = Some(100.0);
+    let column = NodeRef::new(column);
+    column.append(masonry);
+    assert_eq!(cache.max_content_height(&column), 20.0);
+}
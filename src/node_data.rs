@@ -1,3 +1,5 @@
+use transform::Transform2D;
+
 /// Which way the rectangles should flex
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FlexDirection {
@@ -5,8 +7,176 @@ pub enum FlexDirection {
     Row,
 }
 
+/// How a node arranges its children
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LayoutMode {
+    /// Children are stacked along the `flex_direction` (default)
+    Flex,
+    /// Children are `TableRow`s whose cells share the same columns
+    Table,
+    /// A row of a `Table`, children are `TableCell`s. The cells are placed by
+    /// the table, outside of a table this behaves like a `Flex` row.
+    TableRow,
+    /// A cell of a `TableRow`, spanning one or more columns and rows.
+    /// The children of the cell are laid out like in `Flex`.
+    TableCell { colspan: u32, rowspan: u32 },
+    /// Children are placed left to right like words in a paragraph,
+    /// breaking to a new line when the width of the node is exhausted
+    Inline(InlineLayout),
+    /// Children are put into columns, each child goes into the column that
+    /// is currently the shortest (for cards with different heights)
+    Masonry(MasonryLayout),
+    /// The space of the node is divided between the children proportionally
+    /// to their `weight`, trying to keep the children as square as possible
+    Treemap(TreemapLayout),
+    /// Children are placed around a circle or along an arc (pie menus, dials)
+    Radial(RadialLayout),
+    /// The descendants of the node are drawn as a tidy tree diagram (org charts,
+    /// dependency trees), each child is the root of a tree. The layout modes of the
+    /// descendants are ignored, see `tree_diagram`.
+    TreeDiagram(TreeDiagramLayout),
+}
+
+/// Settings for `LayoutMode::TreeDiagram`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreeDiagramLayout {
+    /// Minimum space between two neighbouring nodes on the same level
+    pub sibling_separation: f32,
+    /// Space between two levels of the tree
+    pub level_separation: f32,
+    /// Which way the tree grows
+    pub orientation: TreeOrientation,
+}
+
+impl Default for TreeDiagramLayout {
+    fn default()
+    -> Self
+    {
+        Self {
+            sibling_separation: 10.0,
+            level_separation: 20.0,
+            orientation: TreeOrientation::TopDown,
+        }
+    }
+}
+
+/// Which way a tree diagram grows
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TreeOrientation {
+    /// Root at the top, children below their parent
+    TopDown,
+    /// Root on the left, children to the right of their parent
+    LeftRight,
+}
+
+/// Settings for `LayoutMode::Radial`. Angles are in degrees, 0 degrees is at
+/// the right of the center, positive angles go clockwise.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RadialLayout {
+    /// Angle of the first child
+    pub start_angle: f32,
+    /// How far the children are spread out, 360 for a full circle
+    pub sweep: f32,
+    /// Distance of the center of each child from the center of the node.
+    /// If not set, the children are placed as far out as possible while staying inside the node.
+    pub radius: Option<f32>,
+    /// Whether each child should be rotated so that its top edge faces outward
+    pub rotate_children: bool,
+}
+
+/// Settings for `LayoutMode::Treemap`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreemapLayout {
+    /// Space between the edge of the node and its children, on each level
+    pub padding: f32,
+    /// If true, children that have children of their own (and are `Flex` nodes)
+    /// are subdivided as treemaps as well, with the same settings
+    pub nested: bool,
+}
+
+/// Settings for `LayoutMode::Masonry`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MasonryLayout {
+    /// How many columns there are
+    pub columns: MasonryColumns,
+    /// Space between columns and between the children in a column
+    pub gap: f32,
+}
+
+/// Number of columns in a masonry layout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MasonryColumns {
+    /// Fixed number of columns
+    Count(u32),
+    /// As many columns as fit, each column is at least this wide
+    MinWidth(f32),
+}
+
+/// Settings for `LayoutMode::Inline`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InlineLayout {
+    /// Minimum height of each line. Lines grow to fit their tallest child.
+    pub line_height: Option<f32>,
+    /// How each line is aligned horizontally
+    pub text_align: TextAlign,
+    /// How the children are aligned vertically within a line
+    pub vertical_align: VerticalAlign,
+}
+
+impl Default for InlineLayout {
+    fn default()
+    -> Self
+    {
+        Self {
+            line_height: None,
+            text_align: TextAlign::Start,
+            vertical_align: VerticalAlign::Baseline,
+        }
+    }
+}
+
+/// Horizontal alignment of a line in an inline layout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextAlign {
+    Start,
+    Center,
+    End,
+}
+
+/// Vertical alignment of a child within a line of an inline layout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+    /// First baselines are lined up, children without a baseline sit on it with their bottom edge
+    Baseline,
+}
+
+/// What happens to children that are larger than their parent
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Overflow {
+    /// Children are drawn and hit outside of the node (default)
+    Visible,
+    /// Children are clipped to the node
+    Hidden,
+    /// Children are clipped to the node and moved by the `scroll_offset` of the node
+    Scroll,
+}
+
+/// How the children of a row are aligned on the vertical axis
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlignItems {
+    /// Children start at the top edge of the row (default). Nothing is stretched:
+    /// children without a `height` are as tall as the row anyway, children with a
+    /// `height` keep it.
+    Start,
+    /// Children are shifted down so that their first baselines line up
+    Baseline,
+}
+
 #[derive(Debug, Clone)]
-pub struct NodeData<T: Clone> {
+pub struct NodeData<T> {
     /// Minimum width of this node
     pub min_width: Option<f32>,
     /// Minimum height of this node
@@ -21,11 +191,39 @@ pub struct NodeData<T: Clone> {
     pub height: Option<f32>,
     /// What direction the children should flex to
     pub flex_direction: FlexDirection,
+    /// How the children of this node are arranged
+    pub layout_mode: LayoutMode,
+    /// Nodes with the same width group get the width of the widest member,
+    /// regardless of where they are in the tree
+    pub shared_width_group: Option<String>,
+    /// Nodes with the same height group get the height of the tallest member,
+    /// regardless of where they are in the tree
+    pub shared_height_group: Option<String>,
+    /// Offset of the baseline from the top edge of this node (i.e. reported by
+    /// the text renderer for labels). Containers take it from their children.
+    pub baseline: Option<f32>,
+    /// How the children are aligned if this node is a row
+    pub align_items: AlignItems,
+    /// Weight of the node in a treemap, i.e. the size of a file.
+    /// If not set, the weight is the sum of the weights of the children.
+    pub weight: Option<f32>,
+    /// Whether children outside of this node are clipped / scrolled
+    pub overflow: Overflow,
+    /// How far the content of a `Overflow::Scroll` node is scrolled (x, y)
+    pub scroll_offset: (f32, f32),
+    /// If false, the node is ignored by hit testing (like `pointer-events: none`),
+    /// its children can still be hit
+    pub pointer_events: bool,
+    /// Transform that is applied to this node and all of its descendants after layout
+    pub transform: Option<Transform2D>,
+    /// Point that the transform is applied around, relative to the size of the
+    /// node: (0.0, 0.0) is the top left corner, (0.5, 0.5) the center (default)
+    pub transform_origin: (f32, f32),
     /// Abstract data of the node, defined by the renderer / application (not inside this library)
     pub data: T,
 }
 
-impl<T: Clone> NodeData<T> {
+impl<T> NodeData<T> {
     /// Creates a new node
     pub fn new(min_width: Option<f32>,
                min_height: Option<f32>,
@@ -44,6 +242,17 @@ impl<T: Clone> NodeData<T> {
             width,
             height,
             flex_direction,
+            layout_mode: LayoutMode::Flex,
+            shared_width_group: None,
+            shared_height_group: None,
+            baseline: None,
+            align_items: AlignItems::Start,
+            weight: None,
+            overflow: Overflow::Visible,
+            scroll_offset: (0.0, 0.0),
+            pointer_events: true,
+            transform: None,
+            transform_origin: (0.5, 0.5),
             data,
         }
     }
@@ -60,7 +269,109 @@ impl<T: Clone> NodeData<T> {
             width: None,
             height: None,
             flex_direction: flex_direction,
+            layout_mode: LayoutMode::Flex,
+            shared_width_group: None,
+            shared_height_group: None,
+            baseline: None,
+            align_items: AlignItems::Start,
+            weight: None,
+            overflow: Overflow::Visible,
+            scroll_offset: (0.0, 0.0),
+            pointer_events: true,
+            transform: None,
+            transform_origin: (0.5, 0.5),
             data: data,
         }
     }
+
+    /// Sets how the children of this node are arranged
+    #[inline]
+    pub fn with_layout_mode(mut self, layout_mode: LayoutMode)
+    -> Self
+    {
+        self.layout_mode = layout_mode;
+        self
+    }
+
+    /// Puts the node into a shared width group, i.e. for aligning the label
+    /// column of a form that is built out of separate rows
+    #[inline]
+    pub fn with_shared_width_group(mut self, group: &str)
+    -> Self
+    {
+        self.shared_width_group = Some(group.to_string());
+        self
+    }
+
+    /// Puts the node into a shared height group
+    #[inline]
+    pub fn with_shared_height_group(mut self, group: &str)
+    -> Self
+    {
+        self.shared_height_group = Some(group.to_string());
+        self
+    }
+
+    /// Sets the baseline offset, measured from the top edge of the node
+    #[inline]
+    pub fn with_baseline(mut self, baseline: f32)
+    -> Self
+    {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    /// Sets the weight of the node in a treemap
+    #[inline]
+    pub fn with_weight(mut self, weight: f32)
+    -> Self
+    {
+        self.weight = Some(weight);
+        self
+    }
+
+    /// Sets what happens to children that are larger than the node
+    #[inline]
+    pub fn with_overflow(mut self, overflow: Overflow)
+    -> Self
+    {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Excludes the node from hit testing
+    #[inline]
+    pub fn without_pointer_events(mut self)
+    -> Self
+    {
+        self.pointer_events = false;
+        self
+    }
+
+    /// Sets the transform of the node (applied after layout, inherited by the children)
+    #[inline]
+    pub fn with_transform(mut self, transform: Transform2D)
+    -> Self
+    {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Sets the point that the transform is applied around, relative to the size of the node
+    #[inline]
+    pub fn with_transform_origin(mut self, x: f32, y: f32)
+    -> Self
+    {
+        self.transform_origin = (x, y);
+        self
+    }
+
+    /// Sets how the children of this node are aligned
+    #[inline]
+    pub fn with_align_items(mut self, align_items: AlignItems)
+    -> Self
+    {
+        self.align_items = align_items;
+        self
+    }
 }
@@ -0,0 +1,199 @@
+//! Oriented rectangle: center, half size and angle instead of four corners
+//!
+//! `Rect` stores the four corners directly (which is what the renderer needs),
+//! so every rotation accumulates floating point errors and resizing a rotated
+//! rectangle is not possible. `OrientedRect` stores the rotation as a single
+//! angle, so it can be rotated and resized as often as necessary and only be
+//! converted to corners at the end.
+
+use node_data::NodeData;
+use rect::{Rect, Bounds};
+use transform::Transform2D;
+
+/// A rectangle that is rotated around its center
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OrientedRect {
+    /// x coordinate of the center
+    pub center_x: f32,
+    /// y coordinate of the center
+    pub center_y: f32,
+    /// Half of the width (before rotation)
+    pub half_width: f32,
+    /// Half of the height (before rotation)
+    pub half_height: f32,
+    /// Clockwise rotation in degrees, always in [0, 360)
+    pub angle: f32,
+}
+
+impl OrientedRect {
+
+    /// Creates a new oriented rectangle
+    #[inline]
+    pub fn new(center_x: f32, center_y: f32, width: f32, height: f32, angle: f32)
+    -> Self
+    {
+        Self {
+            center_x,
+            center_y,
+            half_width: width * 0.5,
+            half_height: height * 0.5,
+            angle: normalize_angle(angle),
+        }
+    }
+
+    /// Creates an unrotated rectangle from a layout box
+    #[inline]
+    pub fn from_bounds(bounds: &Bounds)
+    -> Self
+    {
+        Self::new(bounds.left + bounds.width * 0.5, bounds.top + bounds.height * 0.5,
+                  bounds.width, bounds.height, 0.0)
+    }
+
+    /// Recovers the oriented rectangle from the corners of a `Rect`
+    /// (tl, tr, bl, br), i.e. after `Rect::rotate_center`
+    pub fn from_corners(x: &[f32; 4], y: &[f32; 4])
+    -> Self
+    {
+        let center_x = (x[0] + x[1] + x[2] + x[3]) * 0.25;
+        let center_y = (y[0] + y[1] + y[2] + y[3]) * 0.25;
+        let width = (x[1] - x[0]).hypot(y[1] - y[0]);
+        let height = (x[2] - x[0]).hypot(y[2] - y[0]);
+        let angle = (y[1] - y[0]).atan2(x[1] - x[0]).to_degrees();
+        Self::new(center_x, center_y, width, height, angle)
+    }
+
+    /// Width of the rectangle (before rotation)
+    #[inline]
+    pub fn width(&self)
+    -> f32
+    {
+        self.half_width * 2.0
+    }
+
+    /// Height of the rectangle (before rotation)
+    #[inline]
+    pub fn height(&self)
+    -> f32
+    {
+        self.half_height * 2.0
+    }
+
+    /// Unit vector along the top edge (tl -> tr) and along the left edge (tl -> bl)
+    #[inline]
+    fn axes(&self)
+    -> ((f32, f32), (f32, f32))
+    {
+        let (s, c) = self.angle.to_radians().sin_cos();
+        ((c, s), (-s, c))
+    }
+
+    /// Set width, the top left corner stays where it is
+    pub fn set_width(&mut self, width: f32)
+    {
+        let ((ux, uy), _) = self.axes();
+        let delta = width * 0.5 - self.half_width;
+        self.center_x += ux * delta;
+        self.center_y += uy * delta;
+        self.half_width = width * 0.5;
+    }
+
+    /// Set height, the top left corner stays where it is
+    pub fn set_height(&mut self, height: f32)
+    {
+        let (_, (vx, vy)) = self.axes();
+        let delta = height * 0.5 - self.half_height;
+        self.center_x += vx * delta;
+        self.center_y += vy * delta;
+        self.half_height = height * 0.5;
+    }
+
+    /// Moves the rectangle
+    #[inline]
+    pub fn translate(&mut self, x: f32, y: f32)
+    {
+        self.center_x += x;
+        self.center_y += y;
+    }
+
+    /// Rotates the rectangle around its center
+    #[inline]
+    pub fn rotate_center(&mut self, angle: f32)
+    {
+        self.angle = normalize_angle(self.angle + angle);
+    }
+
+    /// Rotates the rectangle around an arbitrary point
+    pub fn rotate_around(&mut self, x: f32, y: f32, angle: f32)
+    {
+        let (s, c) = angle.to_radians().sin_cos();
+        let dx = self.center_x - x;
+        let dy = self.center_y - y;
+        self.center_x = x + dx * c - dy * s;
+        self.center_y = y + dx * s + dy * c;
+        self.rotate_center(angle);
+    }
+
+    /// Returns the corners in the same layout as `Rect`: (x, y), each tl, tr, bl, br
+    pub fn corners(&self)
+    -> ([f32; 4], [f32; 4])
+    {
+        let ((ux, uy), (vx, vy)) = self.axes();
+        let (wx, wy) = (ux * self.half_width, uy * self.half_width);
+        let (hx, hy) = (vx * self.half_height, vy * self.half_height);
+        let (cx, cy) = (self.center_x, self.center_y);
+
+        ([cx - wx - hx, cx + wx - hx, cx - wx + hx, cx + wx + hx],
+         [cy - wy - hy, cy + wy - hy, cy - wy + hy, cy + wy + hy])
+    }
+
+    /// Converts the oriented rectangle into a `Rect` for the renderer
+    #[inline]
+    pub fn to_rect<T>(&self, z: f32, data: NodeData<T>)
+    -> Rect<T>
+    {
+        let (x, y) = self.corners();
+        let to_screen = Transform2D::rotation(self.angle).then(&Transform2D::translation(x[0], y[0]));
+        Rect { x, y, z, data, to_screen }
+    }
+}
+
+impl<'a, T> From<&'a Rect<T>> for OrientedRect {
+    fn from(rect: &'a Rect<T>)
+    -> Self
+    {
+        Self::from_corners(&rect.x, &rect.y)
+    }
+}
+
+/// Brings an angle into [0, 360)
+#[inline]
+fn normalize_angle(angle: f32)
+-> f32
+{
+    let angle = angle % 360.0;
+    if angle < 0.0 { angle + 360.0 } else { angle }
+}
+
+#[test]
+fn oriented_rect_rotates_without_drift() {
+    let original = OrientedRect::new(50.0, 50.0, 40.0, 20.0, 0.0);
+    let mut rect = original;
+
+    for _ in 0..1000 {
+        rect.rotate_center(0.36);
+    }
+    // resizing after the rotation doesn't skew the rectangle
+    rect.set_width(60.0);
+    rect.set_width(40.0);
+
+    let (x, y) = rect.corners();
+    let (original_x, original_y) = original.corners();
+    for i in 0..4 {
+        assert!((x[i] - original_x[i]).abs() < 0.01);
+        assert!((y[i] - original_y[i]).abs() < 0.01);
+    }
+
+    let recovered = OrientedRect::from_corners(&x, &y);
+    assert!((recovered.width() - 40.0).abs() < 0.001 && (recovered.height() - 20.0).abs() < 0.001);
+}
@@ -0,0 +1,311 @@
+//! Rectangle bin packing, for texture atlases and sprite sheets
+//!
+//! Packs a list of sizes into one or more bins of a fixed size. Two heuristics
+//! are available: MaxRects (tighter packing, slower) and Skyline (faster, good
+//! enough for glyphs that are added one after another).
+
+use node_data::NodeData;
+use rect::{Rect, Bounds};
+
+/// Which algorithm is used to find a free space for each item
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PackingHeuristic {
+    /// Keeps a list of all maximal free rectangles, chooses the one where the
+    /// shorter leftover side is the smallest (best short side fit)
+    MaxRects,
+    /// Keeps the top outline of the packed items, places each item as high up
+    /// as possible (bottom-left rule, with y pointing down)
+    Skyline,
+}
+
+/// Settings for packing
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PackingOptions {
+    /// Width of each bin
+    pub bin_width: f32,
+    /// Height of each bin
+    pub bin_height: f32,
+    /// Which algorithm to use
+    pub heuristic: PackingHeuristic,
+    /// Whether items may be rotated by 90 degrees to fit better
+    pub allow_rotation: bool,
+    /// Space between two items (i.e. to prevent texture bleeding)
+    pub padding: f32,
+}
+
+/// Where an item ended up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PackedItem {
+    /// Index of the bin the item was put into
+    pub bin: usize,
+    /// Position and size inside the bin. If the item is rotated, width and height are swapped.
+    pub bounds: Bounds,
+    /// Whether the item was rotated by 90 degrees
+    pub rotated: bool,
+}
+
+impl PackedItem {
+
+    /// Converts the item into a rectangle in the coordinates of its bin
+    #[inline]
+    pub fn into_rect<T>(&self, z: f32, data: NodeData<T>)
+    -> Rect<T>
+    {
+        Rect::new_wh(self.bounds.left, self.bounds.top, self.bounds.width, self.bounds.height, z, data)
+    }
+}
+
+/// Packs the `(width, height)` sizes into as few bins as possible (greedily,
+/// largest items first). Returns one entry per size, in the same order as
+/// `sizes`, `None` if the item doesn't even fit into an empty bin (or its size is NaN).
+pub fn pack(sizes: &[(f32, f32)], options: &PackingOptions)
+-> Vec<Option<PackedItem>>
+{
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let side_a = sizes[a].0.max(sizes[a].1);
+        let side_b = sizes[b].0.max(sizes[b].1);
+        side_b.total_cmp(&side_a)
+    });
+
+    let mut bins = Vec::<Bin>::new();
+    let mut packed = vec![None; sizes.len()];
+
+    for index in order {
+        let (width, height) = sizes[index];
+        // NaN compares as "fits" in the skyline, so it has to be rejected here
+        if width.is_nan() || height.is_nan() {
+            continue;
+        }
+
+        // the bin is padded as well, so that items can touch its right and bottom edge
+        let padded = (width + options.padding, height + options.padding);
+
+        let mut result = bins.iter_mut().enumerate()
+            .filter_map(|(bin_index, bin)| bin.insert(padded, options.allow_rotation).map(|r| (bin_index, r)))
+            .next();
+
+        if result.is_none() {
+            let mut bin = Bin::new(options);
+            if let Some(r) = bin.insert(padded, options.allow_rotation) {
+                bins.push(bin);
+                result = Some((bins.len() - 1, r));
+            }
+        }
+
+        packed[index] = result.map(|(bin, (left, top, rotated))| {
+            let (w, h) = if rotated { (height, width) } else { (width, height) };
+            PackedItem { bin, bounds: Bounds::new(left, top, w, h), rotated }
+        });
+    }
+
+    packed
+}
+
+/// State of a single bin
+enum Bin {
+    MaxRects {
+        free: Vec<Bounds>,
+    },
+    Skyline {
+        width: f32,
+        height: f32,
+        /// (left, top, width) segments of the outline, sorted from left to right
+        segments: Vec<(f32, f32, f32)>,
+    },
+}
+
+impl Bin {
+
+    fn new(options: &PackingOptions)
+    -> Self
+    {
+        let width = options.bin_width + options.padding;
+        let height = options.bin_height + options.padding;
+        match options.heuristic {
+            PackingHeuristic::MaxRects => Bin::MaxRects { free: vec![Bounds::new(0.0, 0.0, width, height)] },
+            PackingHeuristic::Skyline => Bin::Skyline { width, height, segments: vec![(0.0, 0.0, width)] },
+        }
+    }
+
+    /// Tries to insert an item, returns (left, top, rotated) on success
+    fn insert(&mut self, size: (f32, f32), allow_rotation: bool)
+    -> Option<(f32, f32, bool)>
+    {
+        let mut orientations = vec![(size, false)];
+        if allow_rotation && size.0 != size.1 {
+            orientations.push(((size.1, size.0), true));
+        }
+
+        match *self {
+            Bin::MaxRects { ref mut free } => {
+                let (free_index, (width, height), rotated) = orientations.iter()
+                    .flat_map(|&(size, rotated)| free.iter().enumerate().map(move |(i, f)| (i, f, size, rotated)))
+                    .filter(|&(_, f, (w, h), _)| w <= f.width && h <= f.height)
+                    .min_by(|a, b| short_side_fit(a.1, a.2).total_cmp(&short_side_fit(b.1, b.2)))
+                    .map(|(i, _, size, rotated)| (i, size, rotated))?;
+
+                let placed = Bounds::new(free[free_index].left, free[free_index].top, width, height);
+                split_free_rects(free, &placed);
+                Some((placed.left, placed.top, rotated))
+            },
+            Bin::Skyline { width: bin_width, height: bin_height, ref mut segments } => {
+                let mut best: Option<(f32, f32, f32, bool)> = None;
+
+                for &((width, height), rotated) in &orientations {
+                    for start in 0..segments.len() {
+                        let left = segments[start].0;
+                        if left + width > bin_width {
+                            break;
+                        }
+                        let top = skyline_top(segments, start, width);
+                        if top + height > bin_height {
+                            continue;
+                        }
+                        let is_better = best.is_none_or(|(best_left, best_top, _, _)| {
+                            top < best_top || (top == best_top && left < best_left)
+                        });
+                        if is_better {
+                            best = Some((left, top, width, rotated));
+                        }
+                    }
+                }
+
+                let (left, top, width, rotated) = best?;
+                let height = if rotated { size.0 } else { size.1 };
+                add_skyline_segment(segments, (left, top + height, width));
+                Some((left, top, rotated))
+            },
+        }
+    }
+}
+
+/// Leftover on the shorter side if the item is put into the free rectangle (smaller is better)
+fn short_side_fit(free: &Bounds, (width, height): (f32, f32))
+-> f32
+{
+    (free.width - width).min(free.height - height)
+}
+
+/// Splits all free rectangles that overlap the placed item into the (up to four)
+/// maximal rectangles around the item, then removes rectangles that are contained in others
+fn split_free_rects(free: &mut Vec<Bounds>, placed: &Bounds)
+{
+    let mut new_free = Vec::with_capacity(free.len() + 4);
+
+    for f in free.drain(..) {
+        let overlaps = placed.left < f.right() && placed.right() > f.left &&
+                       placed.top < f.bottom() && placed.bottom() > f.top;
+        if !overlaps {
+            new_free.push(f);
+            continue;
+        }
+
+        if placed.left > f.left {
+            new_free.push(Bounds::new(f.left, f.top, placed.left - f.left, f.height));
+        }
+        if placed.right() < f.right() {
+            new_free.push(Bounds::new(placed.right(), f.top, f.right() - placed.right(), f.height));
+        }
+        if placed.top > f.top {
+            new_free.push(Bounds::new(f.left, f.top, f.width, placed.top - f.top));
+        }
+        if placed.bottom() < f.bottom() {
+            new_free.push(Bounds::new(f.left, placed.bottom(), f.width, f.bottom() - placed.bottom()));
+        }
+    }
+
+    let is_contained = |inner: &Bounds, outer: &Bounds| {
+        inner.left >= outer.left && inner.top >= outer.top &&
+        inner.right() <= outer.right() && inner.bottom() <= outer.bottom()
+    };
+
+    for (index, f) in new_free.iter().enumerate() {
+        let redundant = new_free.iter().enumerate().any(|(other_index, other)| {
+            // of two identical rectangles, keep the first one
+            other_index != index && is_contained(f, other) && (!is_contained(other, f) || other_index < index)
+        });
+        if !redundant {
+            free.push(*f);
+        }
+    }
+}
+
+/// Lowest possible top edge of an item of the given width that starts at segment `start`
+fn skyline_top(segments: &[(f32, f32, f32)], start: usize, width: f32)
+-> f32
+{
+    let right = segments[start].0 + width;
+    segments[start..].iter()
+        .take_while(|&&(left, _, _)| left < right)
+        .map(|&(_, top, _)| top)
+        .fold(0.0, f32::max)
+}
+
+/// Adds a new segment on top of the skyline, cutting away the segments below it
+fn add_skyline_segment(segments: &mut Vec<(f32, f32, f32)>, segment: (f32, f32, f32))
+{
+    let (left, _, width) = segment;
+    let right = left + width;
+
+    let mut new_segments = Vec::with_capacity(segments.len() + 1);
+    for &(s_left, s_top, s_width) in segments.iter() {
+        let s_right = s_left + s_width;
+        if s_right <= left || s_left >= right {
+            new_segments.push((s_left, s_top, s_width));
+        } else if s_right > right {
+            // the part of the segment that sticks out on the right remains
+            new_segments.push((right, s_top, s_right - right));
+        }
+    }
+
+    let position = new_segments.iter().position(|&(s_left, _, _)| s_left > left).unwrap_or(new_segments.len());
+    new_segments.insert(position, segment);
+
+    // merge neighbouring segments with the same height
+    segments.clear();
+    for s in new_segments {
+        match segments.last_mut() {
+            Some(last) if last.1 == s.1 => last.2 += s.2,
+            _ => segments.push(s),
+        }
+    }
+}
+
+#[test]
+fn packed_items_do_not_overlap() {
+    let sizes = [(30.0, 20.0), (50.0, 10.0), (20.0, 20.0), (64.0, 64.0), (10.0, 40.0), (25.0, 25.0), (70.0, 5.0)];
+
+    for &heuristic in &[PackingHeuristic::MaxRects, PackingHeuristic::Skyline] {
+        let options = PackingOptions {
+            bin_width: 64.0,
+            bin_height: 64.0,
+            heuristic,
+            allow_rotation: true,
+            padding: 1.0,
+        };
+        let packed = pack(&sizes, &options);
+
+        // 70 x 5 is larger than the bin, even when rotated
+        assert!(packed[6].is_none());
+        // 64 x 64 fills a whole bin
+        assert_eq!(packed[3].unwrap().bounds, Bounds::new(0.0, 0.0, 64.0, 64.0));
+        assert_eq!(packed.iter().filter_map(|item| item.map(|i| i.bin)).max(), Some(1));
+
+        for (index, a) in packed.iter().enumerate().filter_map(|(i, item)| item.map(|a| (i, a))) {
+            assert!(a.bounds.left >= 0.0 && a.bounds.top >= 0.0);
+            assert!(a.bounds.right() <= 64.0 && a.bounds.bottom() <= 64.0);
+
+            for b in packed[index + 1..].iter().filter_map(|item| *item).filter(|b| b.bin == a.bin) {
+                let overlaps = a.bounds.left < b.bounds.right() + 1.0 && a.bounds.right() + 1.0 > b.bounds.left &&
+                               a.bounds.top < b.bounds.bottom() + 1.0 && a.bounds.bottom() + 1.0 > b.bounds.top;
+                assert!(!overlaps, "{:?} overlaps {:?}", a, b);
+            }
+        }
+
+        // sizes from user data can be NaN, such items don't fit anywhere
+        let packed = pack(&[(f32::NAN, f32::NAN), (10.0, 10.0), (f32::NAN, 5.0)], &options);
+        assert!(packed[0].is_none() && packed[2].is_none());
+        assert_eq!(packed[1].unwrap().bounds, Bounds::new(0.0, 0.0, 10.0, 10.0));
+    }
+}
@@ -0,0 +1,168 @@
+//! Placement of floating nodes (popovers, dropdowns, tooltips, context menus)
+//! relative to an anchor, keeping them inside of the viewport
+//!
+//! The floating node is first put on the preferred side of the anchor. If it
+//! overflows the viewport there and there is more space on the opposite side,
+//! it is flipped to the opposite side. Finally it is shifted along the edge of
+//! the anchor until it is inside of the viewport.
+
+use rect::Bounds;
+
+/// Side of the anchor that the floating node is put on.
+/// Start and end are left and right (left-to-right layout).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PopoverSide {
+    Top,
+    Bottom,
+    Start,
+    End,
+}
+
+impl PopoverSide {
+
+    /// The side on the other side of the anchor
+    pub fn opposite(&self)
+    -> Self
+    {
+        match *self {
+            PopoverSide::Top => PopoverSide::Bottom,
+            PopoverSide::Bottom => PopoverSide::Top,
+            PopoverSide::Start => PopoverSide::End,
+            PopoverSide::End => PopoverSide::Start,
+        }
+    }
+}
+
+/// Alignment of the floating node along the edge of the anchor
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PopoverAlign {
+    /// Left / top edges line up
+    Start,
+    Center,
+    /// Right / bottom edges line up
+    End,
+}
+
+/// Settings for placing a floating node
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PopoverOptions {
+    /// Preferred side of the anchor
+    pub side: PopoverSide,
+    /// Alignment along the edge of the anchor
+    pub align: PopoverAlign,
+    /// Distance between the anchor and the floating node
+    pub offset: f32,
+    /// Whether the node may be flipped to the opposite side if it doesn't fit
+    pub flip: bool,
+    /// Whether the node may be shifted along the edge of the anchor to stay inside of the viewport
+    pub shift: bool,
+}
+
+impl Default for PopoverOptions {
+    fn default()
+    -> Self
+    {
+        Self {
+            side: PopoverSide::Bottom,
+            align: PopoverAlign::Start,
+            offset: 0.0,
+            flip: true,
+            shift: true,
+        }
+    }
+}
+
+/// Where the floating node ended up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PopoverPlacement {
+    /// Final position and size of the floating node
+    pub bounds: Bounds,
+    /// Side the node ended up on (differs from the preferred side if it was flipped)
+    pub side: PopoverSide,
+}
+
+/// Places a `width` x `height` floating node next to `anchor`, inside of `viewport`
+pub fn place_popover(anchor: &Bounds, width: f32, height: f32, viewport: &Bounds, options: &PopoverOptions)
+-> PopoverPlacement
+{
+    let mut side = options.side;
+    let mut bounds = position_on_side(anchor, width, height, side, options);
+
+    if options.flip && overflow_on_side(&bounds, viewport, side) > 0.0 {
+        let opposite = side.opposite();
+        let flipped = position_on_side(anchor, width, height, opposite, options);
+        if overflow_on_side(&flipped, viewport, opposite) < overflow_on_side(&bounds, viewport, side) {
+            side = opposite;
+            bounds = flipped;
+        }
+    }
+
+    if options.shift {
+        match side {
+            PopoverSide::Top | PopoverSide::Bottom => {
+                bounds.left = shift_into(bounds.left, bounds.width, viewport.left, viewport.right());
+            },
+            PopoverSide::Start | PopoverSide::End => {
+                bounds.top = shift_into(bounds.top, bounds.height, viewport.top, viewport.bottom());
+            },
+        }
+    }
+
+    PopoverPlacement { bounds, side }
+}
+
+/// Puts the node on the given side of the anchor, without looking at the viewport
+fn position_on_side(anchor: &Bounds, width: f32, height: f32, side: PopoverSide, options: &PopoverOptions)
+-> Bounds
+{
+    let align = |start: f32, anchor_size: f32, size: f32| match options.align {
+        PopoverAlign::Start => start,
+        PopoverAlign::Center => start + (anchor_size - size) * 0.5,
+        PopoverAlign::End => start + anchor_size - size,
+    };
+
+    match side {
+        PopoverSide::Top => Bounds::new(align(anchor.left, anchor.width, width), anchor.top - options.offset - height, width, height),
+        PopoverSide::Bottom => Bounds::new(align(anchor.left, anchor.width, width), anchor.bottom() + options.offset, width, height),
+        PopoverSide::Start => Bounds::new(anchor.left - options.offset - width, align(anchor.top, anchor.height, height), width, height),
+        PopoverSide::End => Bounds::new(anchor.right() + options.offset, align(anchor.top, anchor.height, height), width, height),
+    }
+}
+
+/// How far the node sticks out of the viewport in the direction of the side it is on
+fn overflow_on_side(bounds: &Bounds, viewport: &Bounds, side: PopoverSide)
+-> f32
+{
+    match side {
+        PopoverSide::Top => viewport.top - bounds.top,
+        PopoverSide::Bottom => bounds.bottom() - viewport.bottom(),
+        PopoverSide::Start => viewport.left - bounds.left,
+        PopoverSide::End => bounds.right() - viewport.right(),
+    }
+}
+
+/// Moves a span into [min, max]. If the span is larger than the range, it starts at `min`.
+fn shift_into(start: f32, size: f32, min: f32, max: f32)
+-> f32
+{
+    if start + size > max {
+        (max - size).max(min)
+    } else if start < min {
+        min
+    } else {
+        start
+    }
+}
+
+#[test]
+fn popover_flips_and_shifts_into_viewport() {
+    let viewport = Bounds::new(0.0, 0.0, 200.0, 100.0);
+    let options = PopoverOptions { offset: 4.0, .. PopoverOptions::default() };
+
+    // no space below the anchor, but enough above it
+    let anchor = Bounds::new(180.0, 80.0, 20.0, 10.0);
+    let placement = place_popover(&anchor, 50.0, 30.0, &viewport, &options);
+
+    assert_eq!(placement.side, PopoverSide::Top);
+    assert_eq!(placement.bounds, Bounds::new(150.0, 46.0, 50.0, 30.0));
+}
@@ -0,0 +1,122 @@
+//! Radial layout: children are placed around a circle or along an arc
+
+use tree_node::TreeNode;
+use node_data::{NodeData, RadialLayout};
+use rect::Bounds;
+use measure_cache::MeasureCache;
+
+/// Calculates the position of the children of a radial container inside of `bounds`.
+/// Each child is returned with the angle (in degrees) that it has to be rotated by
+/// to face outward - the rotation is only applied if `rotate_children` is set.
+///
+/// The layout stores the angle in `LayoutResult::rotation` instead of calling
+/// `Rect::rotate_center` on the output rectangle: the rotation is applied with the
+/// node transforms after layout, so the descendants of a child are rotated along
+/// with it and hit testing sees the rotated corners. The corners of the child
+/// itself are the same as with `Rect::rotate_center`.
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(container: &N, bounds: Bounds, layout: &RadialLayout,
+                                                   cache: &mut MeasureCache)
+-> Vec<(N, Bounds, f32)>
+{
+    let children = container.children().map(|node| {
+        let width = cache.max_content_width(&node);
+        let height = cache.max_content_height(&node);
+        (node, width, height)
+    }).collect::<Vec<_>>();
+
+    let center_x = bounds.left + bounds.width * 0.5;
+    let center_y = bounds.top + bounds.height * 0.5;
+
+    let radius = layout.radius.unwrap_or_else(|| {
+        // keep the largest child inside of the container
+        let largest_child = children.iter().map(|&(_, w, h)| w.max(h)).fold(0.0, f32::max);
+        ((bounds.width.min(bounds.height) - largest_child) * 0.5).max(0.0)
+    });
+
+    // a full circle has as many gaps as children, an arc has one gap less
+    // (the first and last child sit on the ends of the arc)
+    let count = children.len();
+    let step = if layout.sweep.abs() >= 360.0 {
+        layout.sweep / count as f32
+    } else if count > 1 {
+        layout.sweep / (count - 1) as f32
+    } else {
+        0.0
+    };
+
+    children.into_iter().enumerate().map(|(index, (node, width, height))| {
+        let angle = layout.start_angle + step * index as f32;
+        let radians = angle.to_radians();
+        let child_center_x = center_x + radius * radians.cos();
+        let child_center_y = center_y + radius * radians.sin();
+        let child_bounds = Bounds::new(child_center_x - width * 0.5, child_center_y - height * 0.5, width, height);
+        // the top edge points up (-90 degrees) when unrotated
+        (node, child_bounds, angle + 90.0)
+    }).collect()
+}
+
+/// Content size of a radial container on one axis, from the content sizes of
+/// its children on the same axis (see `intrinsic::content_size`): the circle
+/// plus the largest child, which sticks out by half of its size on each side.
+/// Without a fixed radius, all children fit on a circle with a radius of 0.
+pub fn content_size(layout: &RadialLayout, children: &[f32])
+-> f32
+{
+    let largest_child = children.iter().cloned().fold(0.0, f32::max);
+    layout.radius.map(|radius| radius.max(0.0) * 2.0).unwrap_or(0.0) + largest_child
+}
+
+#[test]
+fn children_are_placed_on_the_circle() {
+    use rctree::NodeRef;
+    use ui_screen::UiScreen;
+    use node_data::{FlexDirection, LayoutMode};
+    use rect::{Rect, corners_bounding_box};
+    use intrinsic::max_content_width;
+
+    let layout = RadialLayout { start_angle: 0.0, sweep: 360.0, radius: Some(50.0), rotate_children: true };
+    let mut ui_screen = UiScreen::new(200.0, 200.0, NodeData::empty(FlexDirection::Row, 0)
+                                                        .with_layout_mode(LayoutMode::Radial(layout)));
+    let children = (0..4).map(|index| {
+        let mut child = NodeData::empty(FlexDirection::Row, index + 1);
+        child.width = Some(20.0);
+        child.height = Some(10.0);
+        let child = NodeRef::new(child);
+        ui_screen.root.append(child.clone());
+        child
+    }).collect::<Vec<_>>();
+    ui_screen.compute_layout(200.0, 200.0);
+
+    // clockwise from the right of the center, every child faces outward
+    let centers = [(150.0, 100.0), (100.0, 150.0), (50.0, 100.0), (100.0, 50.0)];
+    for (index, child) in children.iter().enumerate() {
+        let result = ui_screen.layout(child).unwrap();
+        let (center_x, center_y) = centers[index];
+        assert!((result.bounds.left + 10.0 - center_x).abs() < 0.001);
+        assert!((result.bounds.top + 5.0 - center_y).abs() < 0.001);
+        assert_eq!(result.rotation, 90.0 * (index + 1) as f32);
+
+        // the corners on the screen are rotated around the center of the child
+        let screen_box = corners_bounding_box(&result.x, &result.y);
+        let (expected_width, expected_height) = if index % 2 == 0 { (10.0, 20.0) } else { (20.0, 10.0) };
+        assert!((screen_box.width - expected_width).abs() < 0.001);
+        assert!((screen_box.height - expected_height).abs() < 0.001);
+        assert!((screen_box.left + screen_box.width * 0.5 - center_x).abs() < 0.001);
+
+        // same corners as a rectangle rotated with `Rect::rotate_center`
+        let mut rect = Rect::new_wh(result.bounds.left, result.bounds.top, 20.0, 10.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+        rect.rotate_center(result.rotation);
+        for corner in 0..4 {
+            assert!((rect.x[corner] - result.x[corner]).abs() < 0.001);
+            assert!((rect.y[corner] - result.y[corner]).abs() < 0.001);
+        }
+    }
+
+    // the content size is a circle with a diameter of 100 plus the widest child
+    let container = NodeRef::new(NodeData::empty(FlexDirection::Row, 0).with_layout_mode(LayoutMode::Radial(layout)));
+    for child in children {
+        child.detach();
+        container.append(child);
+    }
+    assert_eq!(max_content_width(&container), 120.0);
+}
@@ -1,4 +1,4 @@
-use std::cell::{self, RefCell};
+use std::cell::{self, Cell, RefCell};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::rc::{Rc, Weak};
@@ -18,6 +18,9 @@ struct Node<T> {
     last_child: WeakLink<T>,
     previous_sibling: WeakLink<T>,
     next_sibling: Link<T>,
+    /// Set when the data of the node, its children or the data of one of
+    /// its descendants changed since the last layout
+    dirty: Cell<bool>,
     data: T,
 }
 
@@ -64,6 +67,7 @@ impl<T> NodeRef<T> {
             last_child: None,
             previous_sibling: None,
             next_sibling: None,
+            dirty: Cell::new(true),
             data: data,
         })))
     }
@@ -122,20 +126,72 @@ impl<T> NodeRef<T> {
         Ref { _ref: self.0.borrow() }
     }
 
-    /// Return a unique/mutable reference to this node’s data
+    /// Return a unique/mutable reference to this node’s data.
+    /// Marks the node as dirty, see `is_dirty`.
     ///
     /// # Panics
     ///
     /// Panics if the node is currently borrowed.
     pub fn borrow_mut(&self) -> RefMut<T> {
+        self.mark_dirty();
         RefMut { _ref: self.0.borrow_mut() }
     }
 
+    /// Returns whether the node or one of its descendants was changed (borrowed
+    /// mutably, children added or removed) since the last call to `mark_clean`.
+    /// New nodes are dirty.
+    pub fn is_dirty(&self) -> bool {
+        self.0.try_borrow().map(|node| node.dirty.get()).unwrap_or(true)
+    }
+
+    /// Marks the node and all of its ancestors as dirty.
+    pub fn mark_dirty(&self) {
+        let mut current = Some(self.0.clone());
+        while let Some(node_rc) = current {
+            current = match node_rc.try_borrow() {
+                // a dirty node only has dirty ancestors
+                Ok(ref node) if node.dirty.get() => None,
+                Ok(ref node) => {
+                    node.dirty.set(true);
+                    node.parent.as_ref().and_then(|parent| parent.upgrade())
+                },
+                // mutably borrowed nodes were marked as dirty when they were borrowed
+                Err(_) => None,
+            };
+        }
+    }
+
+    /// Clears the dirty flag of this node and all of its dirty descendants.
+    pub fn mark_clean(&self) {
+        self.0.borrow().dirty.set(false);
+        for child in self.children() {
+            if child.is_dirty() {
+                child.mark_clean();
+            }
+        }
+    }
+
+    /// Marks the parent of this node as dirty, before the node is moved
+    fn mark_parent_dirty(&self) {
+        if let Some(parent) = self.parent() {
+            parent.mark_dirty();
+        }
+    }
+
     /// Returns whether two references point to the same node.
     pub fn same_node(&self, other: &NodeRef<T>) -> bool {
         same_rc(&self.0, &other.0)
     }
 
+    /// Returns a number that identifies the node (the address of the node).
+    ///
+    /// The id is only unique as long as the node is alive, the id of a dropped node
+    /// can be reused by a new node.
+    pub fn node_id(&self) -> usize {
+        let ptr: *const RefCell<Node<T>> = &*self.0;
+        ptr as usize
+    }
+
     /// Return an iterator of references to this node and its ancestors.
     ///
     /// Call `.next().unwrap()` once on the iterator to skip the node itself.
@@ -205,6 +261,7 @@ impl<T> NodeRef<T> {
     ///
     /// Panics if the node or one of its adjoining nodes is currently borrowed.
     pub fn detach(&self) {
+        self.mark_parent_dirty();
         self.0.borrow_mut().detach();
     }
 
@@ -214,6 +271,8 @@ impl<T> NodeRef<T> {
     ///
     /// Panics if the node, the new child, or one of their adjoining nodes is currently borrowed.
     pub fn append(&self, new_child: NodeRef<T>) {
+        new_child.mark_parent_dirty();
+        self.mark_dirty();
         let mut self_borrow = self.0.borrow_mut();
         let mut last_child_opt = None;
         {
@@ -246,6 +305,8 @@ impl<T> NodeRef<T> {
     ///
     /// Panics if the node, the new child, or one of their adjoining nodes is currently borrowed.
     pub fn prepend(&self, new_child: NodeRef<T>) {
+        new_child.mark_parent_dirty();
+        self.mark_dirty();
         let mut self_borrow = self.0.borrow_mut();
         {
             let mut new_child_borrow = new_child.0.borrow_mut();
@@ -275,6 +336,8 @@ impl<T> NodeRef<T> {
     ///
     /// Panics if the node, the new sibling, or one of their adjoining nodes is currently borrowed.
     pub fn insert_after(&self, new_sibling: NodeRef<T>) {
+        new_sibling.mark_parent_dirty();
+        self.mark_parent_dirty();
         let mut self_borrow = self.0.borrow_mut();
         {
             let mut new_sibling_borrow = new_sibling.0.borrow_mut();
@@ -312,6 +375,8 @@ impl<T> NodeRef<T> {
     ///
     /// Panics if the node, the new sibling, or one of their adjoining nodes is currently borrowed.
     pub fn insert_before(&self, new_sibling: NodeRef<T>) {
+        new_sibling.mark_parent_dirty();
+        self.mark_parent_dirty();
         let mut self_borrow = self.0.borrow_mut();
         let mut previous_sibling_opt = None;
         {
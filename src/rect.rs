@@ -1,8 +1,9 @@
 use node_data::NodeData;
+use transform::Transform2D;
 
 /// A finite rectangle in pixel coordinates that will end up on the screen
 #[derive(Debug, Clone)]
-pub struct Rect<T: Clone> {
+pub struct Rect<T> {
     /// x coordinates - as an array because of simd layout
     /// tl, tr, bl, br
     pub x: [f32; 4],
@@ -13,9 +14,13 @@ pub struct Rect<T: Clone> {
     pub z: f32,
     /// Internal data of the rectangle
     pub data: NodeData<T>,
+    /// Maps from the local space of the rectangle (the top left corner before any
+    /// rotation or transform is (0, 0)) to screen space. Kept up to date by
+    /// `translate`, `rotate_center`, `rotate_around` and `transform`.
+    pub to_screen: Transform2D,
 }
 
-impl<T: Clone> Rect<T> {
+impl<T> Rect<T> {
 
     /// Creates a new rectangle
     #[inline]
@@ -27,6 +32,7 @@ impl<T: Clone> Rect<T> {
             y: [top, top, bottom, bottom],
             z: z,
             data: data,
+            to_screen: Transform2D::translation(left, top),
         }
     }
 
@@ -46,7 +52,22 @@ impl<T: Clone> Rect<T> {
     {
         let center_y = ((self.y[1] - self.y[2]) * 0.5) + self.y[2];
         let center_x = ((self.x[1] - self.x[2]) * 0.5) + self.x[2];
+        self.rotate_around(center_x, center_y, in_angle);
+    }
+
+    // Rotates the rectangle around its center, using SIMD
+    #[cfg(feature = "use_simd")]
+    pub fn rotate_center(&mut self, in_angle: f32)
+    {
+        let center_y = ((self.y[0] - self.y[2]) * 0.5) + self.y[2];
+        let center_x = ((self.x[1] - self.x[0]) * 0.5) + self.x[0];
+        self.rotate_around(center_x, center_y, in_angle);
+    }
 
+    // Rotates a rectangle around an arbitrary point (i.e. the center of its parent), no SIMD
+    #[cfg(not(feature = "use_simd"))]
+    pub fn rotate_around(&mut self, center_x: f32, center_y: f32, in_angle: f32)
+    {
         self.x[0] -= center_x; self.x[1] -= center_x;
         self.x[2] -= center_x; self.x[3] -= center_x;
 
@@ -73,17 +94,16 @@ impl<T: Clone> Rect<T> {
 
         self.x[0] += center_x; self.x[1] += center_x; self.x[2] += center_x; self.x[3] += center_x;
         self.y[0] += center_y; self.y[1] += center_y; self.y[2] += center_y; self.y[3] += center_y;
+
+        self.to_screen = self.to_screen.then(&Transform2D::rotation(in_angle).around(center_x, center_y));
     }
 
-    // Rotates the rectangle around its center, using SIMD
+    // Rotates a rectangle around an arbitrary point, using SIMD
     #[cfg(feature = "use_simd")]
-    pub fn rotate_center(&mut self, in_angle: f32)
+    pub fn rotate_around(&mut self, center_x: f32, center_y: f32, in_angle: f32)
     {
         use simd;
 
-        let center_y = ((self.y[0] - self.y[2]) * 0.5) + self.y[2];
-        let center_x = ((self.x[1] - self.x[0]) * 0.5) + self.x[0];
-
         let mut simd_x_dir = simd::f32x4::load(&self.x, 0);
         let mut simd_y_dir = simd::f32x4::load(&self.y, 0);
 
@@ -104,6 +124,8 @@ impl<T: Clone> Rect<T> {
 
         simd_x_new.store(&mut self.x, 0);
         simd_y_dir.store(&mut self.y, 0);
+
+        self.to_screen = self.to_screen.then(&Transform2D::rotation(in_angle).around(center_x, center_y));
     }
 
     // Translates a rectangle, no SIMD support
@@ -111,7 +133,9 @@ impl<T: Clone> Rect<T> {
     pub fn translate(&mut self, x: f32, y: f32)
     {
         self.x[0] += x; self.x[1] += x; self.x[2] += x; self.x[3] += x;
-        self.y[0] += y; self.y[1] += y; self.y[2] += y; self.y[3] += x;
+        self.y[0] += y; self.y[1] += y; self.y[2] += y; self.y[3] += y;
+
+        self.to_screen = self.to_screen.then(&Transform2D::translation(x, y));
     }
 
     // Translates a rectangle, with SIMD
@@ -125,12 +149,250 @@ impl<T: Clone> Rect<T> {
 
         simd_x_dir.store(&mut self.x, 0);
         simd_y_dir.store(&mut self.y, 0);
+
+        self.to_screen = self.to_screen.then(&Transform2D::translation(x, y));
+    }
+
+    // Applies an affine transform to all four corners, no SIMD
+    #[cfg(not(feature = "use_simd"))]
+    pub fn transform(&mut self, transform: &Transform2D)
+    {
+        for i in 0..4 {
+            let (x, y) = transform.transform_point(self.x[i], self.y[i]);
+            self.x[i] = x;
+            self.y[i] = y;
+        }
+
+        self.to_screen = self.to_screen.then(transform);
+    }
+
+    // Applies an affine transform to all four corners, using SIMD
+    #[cfg(feature = "use_simd")]
+    pub fn transform(&mut self, transform: &Transform2D)
+    {
+        use simd;
+
+        let simd_x = simd::f32x4::load(&self.x, 0);
+        let simd_y = simd::f32x4::load(&self.y, 0);
+
+        let simd_x_new = simd_x * simd::f32x4::splat(transform.a) +
+                         simd_y * simd::f32x4::splat(transform.c) +
+                         simd::f32x4::splat(transform.e);
+        let simd_y_new = simd_x * simd::f32x4::splat(transform.b) +
+                         simd_y * simd::f32x4::splat(transform.d) +
+                         simd::f32x4::splat(transform.f);
+
+        simd_x_new.store(&mut self.x, 0);
+        simd_y_new.store(&mut self.y, 0);
+
+        self.to_screen = self.to_screen.then(transform);
+    }
+
+    // Axis-aligned bounding box of the (possibly rotated) rectangle, no SIMD
+    #[cfg(not(feature = "use_simd"))]
+    pub fn bounding_box(&self)
+    -> Bounds
+    {
+        corners_bounding_box(&self.x, &self.y)
+    }
+
+    // Axis-aligned bounding box of the (possibly rotated) rectangle, using SIMD
+    #[cfg(feature = "use_simd")]
+    pub fn bounding_box(&self)
+    -> Bounds
+    {
+        use simd;
+
+        let simd_x = simd::f32x4::load(&self.x, 0);
+        let simd_y = simd::f32x4::load(&self.y, 0);
+
+        // compare (tl, tr) with (bl, br), then the two results with each other
+        let simd_x_swapped = simd::f32x4::new(self.x[2], self.x[3], self.x[0], self.x[1]);
+        let simd_y_swapped = simd::f32x4::new(self.y[2], self.y[3], self.y[0], self.y[1]);
+        let min_x = simd_x.min(simd_x_swapped);
+        let max_x = simd_x.max(simd_x_swapped);
+        let min_y = simd_y.min(simd_y_swapped);
+        let max_y = simd_y.max(simd_y_swapped);
+
+        let left = min_x.extract(0).min(min_x.extract(1));
+        let right = max_x.extract(0).max(max_x.extract(1));
+        let top = min_y.extract(0).min(min_y.extract(1));
+        let bottom = max_y.extract(0).max(max_y.extract(1));
+        Bounds::new(left, top, right - left, bottom - top)
+    }
+
+    // Tests if a point is inside of the (possibly rotated) rectangle, no SIMD.
+    // Points on the edge are inside.
+    #[cfg(not(feature = "use_simd"))]
+    pub fn contains_point(&self, x: f32, y: f32)
+    -> bool
+    {
+        corners_contain_point(&self.x, &self.y, x, y)
+    }
+
+    // Tests if a point is inside of the (possibly rotated) rectangle, using SIMD.
+    // Points on the edge are inside.
+    #[cfg(feature = "use_simd")]
+    pub fn contains_point(&self, x: f32, y: f32)
+    -> bool
+    {
+        use simd;
+
+        // see `corners_contain_point` for degenerate rectangles
+        if !self.bounding_box().contains_point(x, y) {
+            return false;
+        }
+
+        // edges tl -> tr, tr -> br, br -> bl, bl -> tl
+        let start_x = simd::f32x4::new(self.x[0], self.x[1], self.x[3], self.x[2]);
+        let start_y = simd::f32x4::new(self.y[0], self.y[1], self.y[3], self.y[2]);
+        let end_x = simd::f32x4::new(self.x[1], self.x[3], self.x[2], self.x[0]);
+        let end_y = simd::f32x4::new(self.y[1], self.y[3], self.y[2], self.y[0]);
+
+        let cross = (end_x - start_x) * (simd::f32x4::splat(y) - start_y) -
+                    (end_y - start_y) * (simd::f32x4::splat(x) - start_x);
+
+        let zero = simd::f32x4::splat(0.0);
+        cross.ge(zero).all() || cross.le(zero).all()
+    }
+
+    /// Area of the rectangle (also correct after rotations).
+    /// Only two edge lengths are needed, so there is no SIMD variant.
+    #[inline]
+    pub fn area(&self)
+    -> f32
+    {
+        let width = (self.x[1] - self.x[0]).hypot(self.y[1] - self.y[0]);
+        let height = (self.x[2] - self.x[0]).hypot(self.y[2] - self.y[0]);
+        width * height
+    }
+
+    // Tests if the other rectangle is completely inside of this rectangle (also after rotations), no SIMD
+    #[cfg(not(feature = "use_simd"))]
+    #[inline]
+    pub fn contains_rect<U>(&self, other: &Rect<U>)
+    -> bool
+    {
+        (0..4).all(|i| self.contains_point(other.x[i], other.y[i]))
+    }
+
+    // Tests if the other rectangle is completely inside of this rectangle (also after rotations),
+    // using SIMD: all four corners of the other rectangle are tested against one edge at a time
+    #[cfg(feature = "use_simd")]
+    pub fn contains_rect<U>(&self, other: &Rect<U>)
+    -> bool
+    {
+        use simd;
+
+        // see `corners_contain_point` for degenerate rectangles
+        if !self.bounding_box().contains(&other.bounding_box()) {
+            return false;
+        }
+
+        let other_x = simd::f32x4::load(&other.x, 0);
+        let other_y = simd::f32x4::load(&other.y, 0);
+        let zero = simd::f32x4::splat(0.0);
+
+        // edges tl -> tr, tr -> br, br -> bl, bl -> tl
+        let edges = [(0, 1), (1, 3), (3, 2), (2, 0)];
+        // all lanes true
+        let mut all_positive = zero.eq(zero);
+        let mut all_negative = all_positive;
+
+        for &(a, b) in &edges {
+            let cross = simd::f32x4::splat(self.x[b] - self.x[a]) * (other_y - simd::f32x4::splat(self.y[a])) -
+                        simd::f32x4::splat(self.y[b] - self.y[a]) * (other_x - simd::f32x4::splat(self.x[a]));
+            all_positive = all_positive & cross.ge(zero);
+            all_negative = all_negative & cross.le(zero);
+        }
+
+        (all_positive | all_negative).all()
+    }
+
+    /// Intersection of the bounding boxes of both rectangles, `None` if the boxes don't overlap.
+    /// Rotated rectangles can be apart while their boxes overlap, see `overlaps`.
+    /// Uses the (SIMD) `bounding_box` of both rectangles, the rest is a few scalar comparisons.
+    #[inline]
+    pub fn bounding_box_intersection<U>(&self, other: &Rect<U>)
+    -> Option<Bounds>
+    {
+        self.bounding_box().intersection(&other.bounding_box())
+    }
+
+    /// Smallest axis-aligned box that contains both rectangles (the union of their bounding boxes).
+    /// Uses the (SIMD) `bounding_box` of both rectangles, the rest is a few scalar comparisons.
+    #[inline]
+    pub fn bounding_box_union<U>(&self, other: &Rect<U>)
+    -> Bounds
+    {
+        self.bounding_box().union(&other.bounding_box())
+    }
+
+    // Separating axis test: do the two (possibly rotated) rectangles overlap? No SIMD.
+    // Rectangles that only touch each other don't overlap.
+    #[cfg(not(feature = "use_simd"))]
+    pub fn overlaps<U>(&self, other: &Rect<U>)
+    -> bool
+    {
+        // the edges tl -> tr and tl -> bl of each rectangle are the possible separating axes
+        let axes = [
+            (self.x[1] - self.x[0], self.y[1] - self.y[0]),
+            (self.x[2] - self.x[0], self.y[2] - self.y[0]),
+            (other.x[1] - other.x[0], other.y[1] - other.y[0]),
+            (other.x[2] - other.x[0], other.y[2] - other.y[0]),
+        ];
+
+        let project = |x: &[f32; 4], y: &[f32; 4], (ax, ay): (f32, f32)| {
+            (0..4).map(|i| x[i] * ax + y[i] * ay)
+                  .fold((f32::MAX, f32::MIN), |(min, max), p| (min.min(p), max.max(p)))
+        };
+
+        axes.iter().all(|&axis| {
+            let (a_min, a_max) = project(&self.x, &self.y, axis);
+            let (b_min, b_max) = project(&other.x, &other.y, axis);
+            a_min < b_max && b_min < a_max
+        })
+    }
+
+    // Separating axis test: do the two (possibly rotated) rectangles overlap? Using SIMD:
+    // the four corners are projected onto an axis at once. Rectangles that only touch each other don't overlap.
+    #[cfg(feature = "use_simd")]
+    pub fn overlaps<U>(&self, other: &Rect<U>)
+    -> bool
+    {
+        use simd;
+
+        // the edges tl -> tr and tl -> bl of each rectangle are the possible separating axes
+        let axes = [
+            (self.x[1] - self.x[0], self.y[1] - self.y[0]),
+            (self.x[2] - self.x[0], self.y[2] - self.y[0]),
+            (other.x[1] - other.x[0], other.y[1] - other.y[0]),
+            (other.x[2] - other.x[0], other.y[2] - other.y[0]),
+        ];
+
+        let self_x = simd::f32x4::load(&self.x, 0);
+        let self_y = simd::f32x4::load(&self.y, 0);
+        let other_x = simd::f32x4::load(&other.x, 0);
+        let other_y = simd::f32x4::load(&other.y, 0);
+
+        let project = |x: simd::f32x4, y: simd::f32x4, (ax, ay): (f32, f32)| {
+            let p = x * simd::f32x4::splat(ax) + y * simd::f32x4::splat(ay);
+            let min = p.extract(0).min(p.extract(1)).min(p.extract(2).min(p.extract(3)));
+            let max = p.extract(0).max(p.extract(1)).max(p.extract(2).max(p.extract(3)));
+            (min, max)
+        };
+
+        axes.iter().all(|&axis| {
+            let (a_min, a_max) = project(self_x, self_y, axis);
+            let (b_min, b_max) = project(other_x, other_y, axis);
+            a_min < b_max && b_min < a_max
+        })
     }
 
     // Convenience function for abstracting over the weird memory layout thing
 
     /// Set width from top left corner
-    /// Warning: may not work well after rotations
+    /// Warning: may not work well after rotations, use `OrientedRect` for rotated rectangles
     #[inline]
     pub fn set_width(&mut self, width: f32)
     {
@@ -139,7 +401,7 @@ impl<T: Clone> Rect<T> {
     }
 
     /// Set height from top left corner
-    /// Warning: may not work well after rotations
+    /// Warning: may not work well after rotations, use `OrientedRect` for rotated rectangles
     #[inline]
     pub fn set_height(&mut self, height: f32)
     {
@@ -148,7 +410,140 @@ impl<T: Clone> Rect<T> {
     }
 }
 
-impl<T: Clone> From<NodeData<T>> for Rect<T> {
+/// Axis-aligned bounding box of four corners (tl, tr, bl, br)
+#[inline]
+pub fn corners_bounding_box(x: &[f32; 4], y: &[f32; 4])
+-> Bounds
+{
+    let left = x[0].min(x[1]).min(x[2].min(x[3]));
+    let right = x[0].max(x[1]).max(x[2].max(x[3]));
+    let top = y[0].min(y[1]).min(y[2].min(y[3]));
+    let bottom = y[0].max(y[1]).max(y[2].max(y[3]));
+    Bounds::new(left, top, right - left, bottom - top)
+}
+
+/// Tests if a point is inside of the quad spanned by four corners (tl, tr, bl, br).
+/// Points on the edge are inside.
+pub fn corners_contain_point(corners_x: &[f32; 4], corners_y: &[f32; 4], x: f32, y: f32)
+-> bool
+{
+    // the cross products of a degenerate quad (no width or height) are 0 for
+    // every point on the same line, so the point has to be in the bounding box too
+    if !corners_bounding_box(corners_x, corners_y).contains_point(x, y) {
+        return false;
+    }
+
+    // walk around the edges tl -> tr -> br -> bl -> tl, the point has to be
+    // on the same side of every edge
+    let order = [0, 1, 3, 2, 0];
+    let mut has_positive = false;
+    let mut has_negative = false;
+
+    for edge in order.windows(2) {
+        let (a, b) = (edge[0], edge[1]);
+        let cross = (corners_x[b] - corners_x[a]) * (y - corners_y[a]) - (corners_y[b] - corners_y[a]) * (x - corners_x[a]);
+        if cross > 0.0 { has_positive = true; }
+        if cross < 0.0 { has_negative = true; }
+    }
+
+    !(has_positive && has_negative)
+}
+
+/// An axis-aligned box, used for passing the space of a node around during layout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    /// Distance of the left edge from the left edge of the screen
+    pub left: f32,
+    /// Distance of the top edge from the top edge of the screen
+    pub top: f32,
+    /// Width of the box
+    pub width: f32,
+    /// Height of the box
+    pub height: f32,
+}
+
+impl Bounds {
+
+    /// Creates a new box
+    #[inline]
+    pub fn new(left: f32, top: f32, width: f32, height: f32)
+    -> Self
+    {
+        Self { left, top, width, height }
+    }
+
+    /// Right edge of the box
+    #[inline]
+    pub fn right(&self)
+    -> f32
+    {
+        self.left + self.width
+    }
+
+    /// Bottom edge of the box
+    #[inline]
+    pub fn bottom(&self)
+    -> f32
+    {
+        self.top + self.height
+    }
+
+    /// Area of the box
+    #[inline]
+    pub fn area(&self)
+    -> f32
+    {
+        self.width * self.height
+    }
+
+    /// Tests if a point is inside of the box (edges are inside)
+    #[inline]
+    pub fn contains_point(&self, x: f32, y: f32)
+    -> bool
+    {
+        x >= self.left && x <= self.right() && y >= self.top && y <= self.bottom()
+    }
+
+    /// Tests if the other box is completely inside of this box
+    #[inline]
+    pub fn contains(&self, other: &Bounds)
+    -> bool
+    {
+        other.left >= self.left && other.top >= self.top &&
+        other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+
+    /// Overlapping part of both boxes, `None` if they don't overlap
+    #[inline]
+    pub fn intersection(&self, other: &Bounds)
+    -> Option<Bounds>
+    {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right > left && bottom > top {
+            Some(Bounds::new(left, top, right - left, bottom - top))
+        } else {
+            None
+        }
+    }
+
+    /// Smallest box that contains both boxes
+    #[inline]
+    pub fn union(&self, other: &Bounds)
+    -> Bounds
+    {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Bounds::new(left, top, right - left, bottom - top)
+    }
+}
+
+impl<T> From<NodeData<T>> for Rect<T> {
     fn from(data: NodeData<T>)
     -> Self
     {
@@ -157,28 +552,65 @@ impl<T: Clone> From<NodeData<T>> for Rect<T> {
             y: [0.0; 4],
             z: 0.0,
             data: data,
+            to_screen: Transform2D::identity(),
         }
     }
 }
 
-// without SIMD: ~23 µs
-// with SIMD: ~14 µs
-#[bench]
-fn bench_rotate_center(b: &mut test::Bencher) {
-    let mut rect = Rect::new(200.0, 400.0, 400.0, 600.0, 0.0, DebugColor::yellow());
+#[test]
+fn rotated_rect_geometry() {
+    use node_data::FlexDirection;
+
+    let mut rect = Rect::new_wh(0.0, 0.0, 20.0, 20.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    rect.rotate_center(45.0);
+
+    // the corner at (1, 1) is outside of the rotated square, its bounding box grows to 20 * sqrt(2)
+    assert!(!rect.contains_point(1.0, 1.0));
+    assert!(rect.contains_point(10.0, 0.0));
+    assert!((rect.bounding_box().width - 28.284).abs() < 0.01);
+    assert!((rect.area() - 400.0).abs() < 0.01);
 
-    b.iter(|| {
-        rect.rotate_center(rand::random::<f32>());
-    })
+    let small = Rect::new_wh(0.0, 0.0, 2.0, 2.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    // the small square is in the corner of the bounding box, outside of the rotated square
+    assert!(!rect.overlaps(&small));
+    assert!(rect.bounding_box_intersection(&small).is_some());
+    assert!(rect.bounding_box_union(&small).contains(&small.bounding_box()));
+
+    let inner = Rect::new_wh(8.0, 8.0, 4.0, 4.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    assert!(rect.overlaps(&inner) && rect.contains_rect(&inner));
 }
 
-// without SIMD: ~3 µs
-// with SIMD: ~1.4 µs
-#[bench]
-fn bench_translate(b: &mut test::Bencher) {
-    let mut rect = Rect::new(200.0, 400.0, 400.0, 600.0, 0.0, DebugColor::yellow());
+#[test]
+fn translate_moves_all_corners() {
+    use node_data::FlexDirection;
+
+    let mut rect = Rect::new_wh(10.0, 20.0, 30.0, 40.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    rect.translate(5.0, -3.0);
+
+    // the last y coordinate used to be moved by x instead of y
+    assert_eq!(rect.x, [15.0, 45.0, 15.0, 45.0]);
+    assert_eq!(rect.y, [17.0, 17.0, 57.0, 57.0]);
+    assert_eq!(rect.bounding_box(), Bounds::new(15.0, 17.0, 30.0, 40.0));
+}
 
-    b.iter(|| {
-        rect.translate(rand::random::<f32>(), rand::random::<f32>());
-    })
+#[test]
+fn degenerate_rects_only_contain_their_own_points() {
+    use node_data::FlexDirection;
+
+    // all four corners are the same point
+    let empty = Rect::new_wh(10.0, 10.0, 0.0, 0.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    assert!(empty.contains_point(10.0, 10.0));
+    assert!(!empty.contains_point(50.0, 50.0));
+    assert!(!empty.contains_point(-50.0, 10.0));
+
+    // a line from (10, 10) to (30, 10)
+    let line = Rect::new_wh(10.0, 10.0, 20.0, 0.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    assert!(line.contains_point(20.0, 10.0));
+    assert!(!line.contains_point(50.0, 10.0));
+    assert!(!line.contains_point(20.0, 11.0));
+
+    let inner = Rect::new_wh(15.0, 10.0, 5.0, 0.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+    assert!(line.contains_rect(&inner));
+    assert!(!empty.contains_rect(&line));
+    assert!(!line.overlaps(&empty));
 }
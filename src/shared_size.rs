@@ -0,0 +1,68 @@
+//! Shared size groups: nodes in different branches of the tree that should
+//! end up with the same width or height (i.e. the label column of a form)
+
+use std::collections::HashMap;
+use tree_node::TreeNode;
+use node_data::NodeData;
+use layout_result::LayoutResults;
+
+/// The resolved size of every shared size group, taken from the largest member
+#[derive(Debug, Clone, Default)]
+pub struct SharedSizes {
+    /// Width of each width group
+    pub widths: HashMap<String, f32>,
+    /// Height of each height group
+    pub heights: HashMap<String, f32>,
+}
+
+impl SharedSizes {
+
+    /// Collects the largest width / height of each group from a finished layout
+    pub fn collect<T, N: TreeNode<Data = NodeData<T>>>(root: &N, results: &LayoutResults)
+    -> Self
+    {
+        let mut sizes = Self::default();
+
+        for node in root.descendants() {
+            let bounds = match results.get(&node) {
+                Some(result) => result.bounds,
+                None => continue,
+            };
+            let data = node.borrow();
+            if let Some(ref group) = data.shared_width_group {
+                let entry = sizes.widths.entry(group.clone()).or_insert(bounds.width);
+                if bounds.width > *entry { *entry = bounds.width; }
+            }
+            if let Some(ref group) = data.shared_height_group {
+                let entry = sizes.heights.entry(group.clone()).or_insert(bounds.height);
+                if bounds.height > *entry { *entry = bounds.height; }
+            }
+        }
+
+        sizes
+    }
+
+    /// Returns if there are no groups at all (no second layout pass is needed)
+    #[inline]
+    pub fn is_empty(&self)
+    -> bool
+    {
+        self.widths.is_empty() && self.heights.is_empty()
+    }
+
+    /// Width of the group, if the group exists
+    #[inline]
+    pub fn width(&self, group: &Option<String>)
+    -> Option<f32>
+    {
+        group.as_ref().and_then(|g| self.widths.get(g).cloned())
+    }
+
+    /// Height of the group, if the group exists
+    #[inline]
+    pub fn height(&self, group: &Option<String>)
+    -> Option<f32>
+    {
+        group.as_ref().and_then(|g| self.heights.get(g).cloned())
+    }
+}
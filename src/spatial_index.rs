@@ -0,0 +1,321 @@
+//! Spatial index (bounding volume hierarchy) over the output of a layout
+//!
+//! Testing every rectangle on each mouse move gets slow on screens with tens of
+//! thousands of nodes. The index sorts the rectangles into a tree of boxes, so
+//! that a query only has to look at the few rectangles near the point.
+//!
+//! The index takes ownership of the rectangles from `UiScreen::into_rectangles`.
+//! After the next layout, pass the new rectangles to `update` - as long as the
+//! number of nodes didn't change, the existing tree is only refitted (the boxes
+//! are recomputed, the tree itself is kept), which is a lot cheaper than a rebuild.
+
+use rect::{Rect, Bounds};
+
+/// Maximum number of rectangles in a leaf
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Copy, Clone)]
+enum BvhNodeKind {
+    /// Range in `SpatialIndex::order`
+    Leaf { start: usize, count: usize },
+    /// Indices of the two children in `SpatialIndex::nodes`
+    Branch { left: usize, right: usize },
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BvhNode {
+    bounds: Bounds,
+    kind: BvhNodeKind,
+}
+
+/// Bounding volume hierarchy over a list of (possibly rotated) rectangles.
+/// All queries return indices into `rects()`.
+#[derive(Debug)]
+pub struct SpatialIndex<T> {
+    rects: Vec<Rect<T>>,
+    /// Bounding box of each rectangle, same order as `rects`
+    boxes: Vec<Bounds>,
+    /// Indices into `rects`, grouped so that every leaf is a continuous range
+    order: Vec<usize>,
+    /// Nodes of the tree, the root is the first node and children always come after their parent
+    nodes: Vec<BvhNode>,
+}
+
+impl<T> SpatialIndex<T> {
+
+    /// Builds the index over the given rectangles
+    pub fn new(rects: Vec<Rect<T>>)
+    -> Self
+    {
+        let mut index = Self {
+            rects,
+            boxes: Vec::new(),
+            order: Vec::new(),
+            nodes: Vec::new(),
+        };
+        index.rebuild();
+        index
+    }
+
+    /// The indexed rectangles
+    #[inline]
+    pub fn rects(&self)
+    -> &[Rect<T>]
+    {
+        &self.rects
+    }
+
+    /// Replaces the rectangles with the result of a new layout. Refits the tree if the
+    /// number of rectangles is the same, otherwise the tree is rebuilt.
+    pub fn update(&mut self, rects: Vec<Rect<T>>)
+    {
+        let same_count = rects.len() == self.rects.len();
+        self.rects = rects;
+        if same_count {
+            self.refit();
+        } else {
+            self.rebuild();
+        }
+    }
+
+    /// Builds the tree from scratch. Call this instead of `update` if the
+    /// nodes moved so much that the refitted tree got slow.
+    pub fn rebuild(&mut self)
+    {
+        self.boxes = self.rects.iter().map(|rect| rect.bounding_box()).collect();
+        self.order = (0..self.rects.len()).collect();
+        self.nodes.clear();
+
+        if !self.rects.is_empty() {
+            let count = self.order.len();
+            self.build_node(0, count);
+        }
+    }
+
+    /// Recomputes the boxes of the tree, keeping the tree itself
+    fn refit(&mut self)
+    {
+        self.boxes = self.rects.iter().map(|rect| rect.bounding_box()).collect();
+
+        // children come after their parent, so going backwards
+        // updates all children before the parent
+        for i in (0..self.nodes.len()).rev() {
+            let bounds = match self.nodes[i].kind {
+                BvhNodeKind::Leaf { start, count } => self.range_bounds(start, count),
+                BvhNodeKind::Branch { left, right } => self.nodes[left].bounds.union(&self.nodes[right].bounds),
+            };
+            self.nodes[i].bounds = bounds;
+        }
+    }
+
+    /// Builds the node for `order[start..start + count]`, returns the index of the node
+    fn build_node(&mut self, start: usize, count: usize)
+    -> usize
+    {
+        let bounds = self.range_bounds(start, count);
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode { bounds, kind: BvhNodeKind::Leaf { start, count } });
+
+        if count <= LEAF_SIZE {
+            return node_index;
+        }
+
+        // split at the median of the centers, along the longer axis
+        {
+            let boxes = &self.boxes;
+            let center = |i: &usize| if bounds.width >= bounds.height {
+                boxes[*i].left + boxes[*i].width * 0.5
+            } else {
+                boxes[*i].top + boxes[*i].height * 0.5
+            };
+            self.order[start..start + count].sort_by(|a, b| center(a).total_cmp(&center(b)));
+        }
+
+        let half = count / 2;
+        let left = self.build_node(start, half);
+        let right = self.build_node(start + half, count - half);
+        self.nodes[node_index].kind = BvhNodeKind::Branch { left, right };
+        node_index
+    }
+
+    /// Bounding box of `order[start..start + count]`
+    fn range_bounds(&self, start: usize, count: usize)
+    -> Bounds
+    {
+        let mut indices = self.order[start..start + count].iter();
+        let first = self.boxes[*indices.next().unwrap()];
+        indices.fold(first, |bounds, i| bounds.union(&self.boxes[*i]))
+    }
+
+    /// Calls `f` with every rectangle whose leaf passes `visit_box`
+    fn walk<V, F>(&self, visit_box: V, mut f: F)
+    where V: Fn(&Bounds) -> bool, F: FnMut(usize)
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !visit_box(&node.bounds) {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { start, count } => {
+                    for i in &self.order[start..start + count] {
+                        f(*i);
+                    }
+                },
+                BvhNodeKind::Branch { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                },
+            }
+        }
+    }
+
+    /// Returns all rectangles that contain the point, in no particular order.
+    /// Rotated rectangles are tested against their actual corners.
+    pub fn query_point(&self, x: f32, y: f32)
+    -> Vec<usize>
+    {
+        let mut found = Vec::new();
+        self.walk(|bounds| bounds.contains_point(x, y), |i| {
+            if self.boxes[i].contains_point(x, y) && self.rects[i].contains_point(x, y) {
+                found.push(i);
+            }
+        });
+        found
+    }
+
+    /// Returns the topmost rectangle under the point that accepts pointer events
+    /// (highest z-index, the later one if the z-index is the same).
+    ///
+    /// The index only knows the flat rectangles, so unlike `UiScreen::hit_test`,
+    /// this doesn't clip children of `Overflow::Hidden` nodes.
+    pub fn topmost(&self, x: f32, y: f32)
+    -> Option<usize>
+    {
+        // a NaN z-index is below every other z-index, instead of above (like in `total_cmp`)
+        let z = |i: &usize| {
+            let z = self.rects[*i].z;
+            if z.is_nan() { f32::NEG_INFINITY } else { z }
+        };
+        self.query_point(x, y).into_iter()
+            .filter(|i| self.rects[*i].data.pointer_events)
+            .max_by(|a, b| z(a).total_cmp(&z(b)).then(a.cmp(b)))
+    }
+
+    /// Returns all rectangles whose bounding box overlaps the area, in no particular order
+    pub fn query_rect(&self, area: &Bounds)
+    -> Vec<usize>
+    {
+        let mut found = Vec::new();
+        self.walk(|bounds| bounds.intersection(area).is_some(), |i| {
+            if self.boxes[i].intersection(area).is_some() {
+                found.push(i);
+            }
+        });
+        found
+    }
+
+    /// Returns the rectangle closest to the point and its distance. The distance is
+    /// measured to the bounding box of the rectangle and is 0 if the point is inside.
+    pub fn nearest(&self, x: f32, y: f32)
+    -> Option<(usize, f32)>
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<(usize, f32)> = None;
+        let mut stack = vec![0];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if best.is_some_and(|(_, distance)| box_distance(&node.bounds, x, y) >= distance) {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { start, count } => {
+                    for i in &self.order[start..start + count] {
+                        let distance = box_distance(&self.boxes[*i], x, y);
+                        if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                            best = Some((*i, distance));
+                        }
+                    }
+                },
+                BvhNodeKind::Branch { left, right } => {
+                    // visit the closer child first, so more of the other one gets skipped
+                    let (near, far) = if box_distance(&self.nodes[left].bounds, x, y) <= box_distance(&self.nodes[right].bounds, x, y) {
+                        (left, right)
+                    } else {
+                        (right, left)
+                    };
+                    stack.push(far);
+                    stack.push(near);
+                },
+            }
+        }
+
+        best
+    }
+}
+
+/// Distance from the point to the box, 0 if the point is inside
+#[inline]
+fn box_distance(bounds: &Bounds, x: f32, y: f32)
+-> f32
+{
+    let dx = (bounds.left - x).max(x - bounds.right()).max(0.0);
+    let dy = (bounds.top - y).max(y - bounds.bottom()).max(0.0);
+    dx.hypot(dy)
+}
+
+#[test]
+fn spatial_index_queries_match_brute_force() {
+    use node_data::{NodeData, FlexDirection};
+
+    // 30 x 30 grid of 10 x 10 cells with a 5 unit gap, every 7th cell rotated
+    let mut rects = Vec::new();
+    for row in 0..30 {
+        for column in 0..30 {
+            let mut rect = Rect::new_wh(column as f32 * 15.0, row as f32 * 15.0, 10.0, 10.0, 0.0, NodeData::empty(FlexDirection::Row, ()));
+            if (row * 30 + column) % 7 == 0 {
+                rect.rotate_center(45.0);
+            }
+            rects.push(rect);
+        }
+    }
+
+    let mut index = SpatialIndex::new(rects.clone());
+    for &(x, y) in &[(5.0, 5.0), (12.5, 12.5), (100.0, 230.0), (1.0, 1.0), (-50.0, 40.0)] {
+        let mut found = index.query_point(x, y);
+        found.sort();
+        let expected = (0..rects.len()).filter(|i| rects[*i].contains_point(x, y)).collect::<Vec<_>>();
+        assert_eq!(found, expected);
+    }
+
+    assert_eq!(index.query_rect(&Bounds::new(14.0, 14.0, 2.0, 2.0)).len(), 1);
+    assert_eq!(index.nearest(-50.0, 40.0).map(|(i, _)| i), Some(60));
+
+    // refitting after everything moved
+    for rect in &mut rects {
+        rect.translate(1000.0, 0.0);
+    }
+    index.update(rects);
+    assert!(index.query_point(5.0, 5.0).is_empty());
+    assert_eq!(index.topmost(1005.0, 5.0), Some(0));
+
+    // NaN positions and z-indices from user data don't panic when building or querying
+    // (more rectangles than fit into a leaf, so that they have to be sorted)
+    let mut nan_rects = (0..LEAF_SIZE).map(|i| {
+        Rect::new_wh(if i % 2 == 0 { f32::NAN } else { i as f32 * 20.0 }, 0.0, 10.0, 10.0, 0.0, NodeData::empty(FlexDirection::Row, ()))
+    }).collect::<Vec<_>>();
+    nan_rects.push(Rect::new_wh(0.0, 0.0, 10.0, 10.0, f32::NAN, NodeData::empty(FlexDirection::Row, ())));
+    nan_rects.push(Rect::new_wh(0.0, 0.0, 10.0, 10.0, -1.0, NodeData::empty(FlexDirection::Row, ())));
+    let index = SpatialIndex::new(nan_rects);
+    // the rectangle with a NaN z-index is below the one with a negative z-index
+    assert_eq!(index.topmost(5.0, 5.0), Some(LEAF_SIZE + 1));
+}
@@ -0,0 +1,265 @@
+//! Table layout: the children of a `LayoutMode::Table` node are rows, the
+//! children of the rows are cells. All rows share the same columns, the width
+//! of each column is calculated from the min-content / max-content widths of
+//! all cells in that column, across all rows.
+
+use tree_node::TreeNode;
+use node_data::{NodeData, LayoutMode};
+use rect::Bounds;
+use measure_cache::MeasureCache;
+
+/// A row of a table, together with the position of its cells
+#[derive(Debug)]
+pub struct TableRowBox<N> {
+    /// The row node
+    pub row: N,
+    /// Where the row ends up (spans all columns)
+    pub bounds: Bounds,
+    /// The cells that start in this row and where they end up
+    pub cells: Vec<(N, Bounds)>,
+}
+
+/// A cell with its position in the table grid
+struct CellSlot<N> {
+    node: N,
+    row: usize,
+    column: usize,
+    colspan: usize,
+    rowspan: usize,
+}
+
+/// Calculates the position of all rows and cells of a table inside of `bounds`,
+/// the content sizes of the cells are measured with `cache`
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(table: &N, bounds: Bounds, cache: &mut MeasureCache)
+-> Vec<TableRowBox<N>>
+{
+    let rows = table.children().collect::<Vec<_>>();
+    let (cells, column_count) = place_cells(&rows);
+
+    let column_widths = resolve_column_widths(&cells, column_count, bounds.width, cache);
+    let row_heights = resolve_row_heights(&rows, &cells, &column_widths, bounds.height, cache);
+
+    let column_offsets = offsets(bounds.left, &column_widths);
+    let row_offsets = offsets(bounds.top, &row_heights);
+    let table_width = column_widths.iter().sum();
+
+    let mut row_boxes = rows.iter().enumerate().map(|(index, row)| {
+        TableRowBox {
+            row: row.clone(),
+            bounds: Bounds::new(bounds.left, row_offsets[index], table_width, row_heights[index]),
+            cells: Vec::new(),
+        }
+    }).collect::<Vec<_>>();
+
+    for cell in cells {
+        let width = column_widths[cell.column..cell.column + cell.colspan].iter().sum();
+        let height = row_heights[cell.row..cell.row + cell.rowspan].iter().sum();
+        let cell_bounds = Bounds::new(column_offsets[cell.column], row_offsets[cell.row], width, height);
+        row_boxes[cell.row].cells.push((cell.node, cell_bounds));
+    }
+
+    row_boxes
+}
+
+/// Assigns every cell to a column, skipping columns that are still occupied
+/// by cells with a rowspan from a previous row. Returns the cells and the number of columns.
+fn place_cells<T, N: TreeNode<Data = NodeData<T>>>(rows: &[N])
+-> (Vec<CellSlot<N>>, usize)
+{
+    let mut cells = Vec::new();
+    // how many more rows each column is occupied for
+    let mut occupied = Vec::<usize>::new();
+    // a cell can't span more columns than the widest row has cells, the
+    // columns after that would be empty (and a huge colspan would allocate them)
+    let max_colspan = rows.iter().map(|row| row.children().count()).max().unwrap_or(1).max(1);
+
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut column = 0;
+
+        for cell in row.children() {
+            let (colspan, rowspan) = match cell.borrow().layout_mode {
+                LayoutMode::TableCell { colspan, rowspan } => (colspan.max(1) as usize, rowspan.max(1) as usize),
+                _ => (1, 1),
+            };
+            let colspan = colspan.min(max_colspan);
+            let rowspan = rowspan.min(rows.len() - row_index);
+
+            while column < occupied.len() && occupied[column] > 0 {
+                column += 1;
+            }
+
+            if occupied.len() < column + colspan {
+                occupied.resize(column + colspan, 0);
+            }
+
+            for slot in &mut occupied[column..column + colspan] {
+                *slot = (*slot).max(rowspan);
+            }
+
+            cells.push(CellSlot { node: cell, row: row_index, column, colspan, rowspan });
+            column += colspan;
+        }
+
+        for slot in &mut occupied {
+            if *slot > 0 { *slot -= 1; }
+        }
+    }
+
+    let column_count = occupied.len();
+    (cells, column_count)
+}
+
+/// Distributes the available width between the columns: columns are never
+/// smaller than their min-content width, between min-content and max-content
+/// the width is interpolated, left over space is distributed proportionally
+fn resolve_column_widths<T, N: TreeNode<Data = NodeData<T>>>(cells: &[CellSlot<N>], column_count: usize, available_width: f32,
+                                                             cache: &mut MeasureCache)
+-> Vec<f32>
+{
+    let mut min_widths = vec![0.0_f32; column_count];
+    let mut max_widths = vec![0.0_f32; column_count];
+
+    // single-column cells first, so that spanning cells only add the width that is still missing
+    let mut sorted_cells = cells.iter().collect::<Vec<_>>();
+    sorted_cells.sort_by_key(|cell| cell.colspan);
+
+    for cell in sorted_cells {
+        let columns = cell.column..cell.column + cell.colspan;
+        grow_span(&mut min_widths[columns.clone()], cache.min_content_width(&cell.node));
+        grow_span(&mut max_widths[columns], cache.max_content_width(&cell.node));
+    }
+
+    for (max, min) in max_widths.iter_mut().zip(min_widths.iter()) {
+        if *max < *min { *max = *min; }
+    }
+
+    let sum_min: f32 = min_widths.iter().sum();
+    let sum_max: f32 = max_widths.iter().sum();
+
+    if available_width <= sum_min {
+        min_widths
+    } else if available_width <= sum_max {
+        let factor = (available_width - sum_min) / (sum_max - sum_min);
+        min_widths.iter().zip(max_widths.iter()).map(|(min, max)| min + (max - min) * factor).collect()
+    } else {
+        distribute(&mut max_widths, available_width - sum_max);
+        max_widths
+    }
+}
+
+/// Calculates the height of each row from the row itself and the min-content
+/// height of its cells (at the width of their columns), left over space is
+/// distributed evenly between the rows
+fn resolve_row_heights<T, N: TreeNode<Data = NodeData<T>>>(rows: &[N], cells: &[CellSlot<N>], column_widths: &[f32],
+                                                           available_height: f32, cache: &mut MeasureCache)
+-> Vec<f32>
+{
+    let mut heights = rows.iter().map(|row| {
+        let data = row.borrow();
+        data.height.or(data.min_height).unwrap_or(0.0)
+    }).collect::<Vec<_>>();
+
+    let mut sorted_cells = cells.iter().collect::<Vec<_>>();
+    sorted_cells.sort_by_key(|cell| cell.rowspan);
+
+    for cell in sorted_cells {
+        let width = column_widths[cell.column..cell.column + cell.colspan].iter().sum();
+        grow_span(&mut heights[cell.row..cell.row + cell.rowspan], cache.min_content_height_at(&cell.node, width));
+    }
+
+    let sum: f32 = heights.iter().sum();
+    if available_height > sum && !heights.is_empty() {
+        let extra = (available_height - sum) / heights.len() as f32;
+        for height in &mut heights {
+            *height += extra;
+        }
+    }
+
+    heights
+}
+
+/// Makes sure that the sizes in `span` add up to at least `size`,
+/// the missing size is distributed evenly
+fn grow_span(span: &mut [f32], size: f32)
+{
+    let current: f32 = span.iter().sum();
+    if size > current {
+        let extra = (size - current) / span.len() as f32;
+        for item in span.iter_mut() {
+            *item += extra;
+        }
+    }
+}
+
+/// Distributes extra space proportionally to the current sizes
+/// (or evenly if all sizes are zero)
+fn distribute(sizes: &mut [f32], extra: f32)
+{
+    let sum: f32 = sizes.iter().sum();
+    let count = sizes.len() as f32;
+    for size in sizes.iter_mut() {
+        *size += if sum > 0.0 { extra * *size / sum } else { extra / count };
+    }
+}
+
+/// Converts sizes into start offsets
+fn offsets(start: f32, sizes: &[f32])
+-> Vec<f32>
+{
+    sizes.iter().scan(start, |offset, size| {
+        let current = *offset;
+        *offset += size;
+        Some(current)
+    }).collect()
+}
+
+#[test]
+fn table_columns_are_shared_between_rows() {
+    use rctree::NodeRef;
+    use node_data::{NodeData, FlexDirection};
+
+    let cell = |min_width: f32, max_width: f32, colspan: u32| {
+        let mut data = NodeData::empty(FlexDirection::Row, ())
+            .with_layout_mode(LayoutMode::TableCell { colspan, rowspan: 1 });
+        data.min_width = Some(min_width);
+        data.max_width = Some(max_width);
+        NodeRef::new(data)
+    };
+    let row = || NodeRef::new(NodeData::empty(FlexDirection::Row, ()).with_layout_mode(LayoutMode::TableRow));
+
+    let table = NodeRef::new(NodeData::empty(FlexDirection::Column, ()).with_layout_mode(LayoutMode::Table));
+    let first_row = row();
+    first_row.append(cell(50.0, 100.0, 1));
+    first_row.append(cell(20.0, 20.0, 1));
+    let second_row = row();
+    second_row.append(cell(10.0, 10.0, 1));
+    second_row.append(cell(30.0, 50.0, 1));
+    let third_row = row();
+    third_row.append(cell(60.0, 60.0, 2));
+    table.append(first_row);
+    table.append(second_row);
+    table.append(third_row);
+
+    // max-content is 100 + 50 = 150, 30 pixels of space left per row
+    let rows = arrange(&table, Bounds::new(0.0, 0.0, 180.0, 90.0), &mut MeasureCache::new());
+    let widths = rows.iter().map(|row| row.cells.iter().map(|(_, b)| b.width).collect::<Vec<_>>()).collect::<Vec<_>>();
+    assert_eq!(widths[0], widths[1]);
+    assert_eq!(widths[2], vec![widths[0][0] + widths[0][1]]);
+    assert_eq!(rows[0].cells[1].1.left, rows[1].cells[1].1.left);
+
+    assert_eq!(widths[0], vec![120.0, 60.0]);
+
+    // too narrow: columns don't get smaller than their min-content width (50 + 30)
+    let rows = arrange(&table, Bounds::new(0.0, 0.0, 50.0, 90.0), &mut MeasureCache::new());
+    assert_eq!(rows[0].bounds.width, 80.0);
+
+    // spans larger than the table are cut off at the widest row (2 cells)
+    let fourth_row = row();
+    fourth_row.append(cell(10.0, 10.0, u32::MAX));
+    table.append(fourth_row);
+    let (cells, column_count) = place_cells(&table.children().collect::<Vec<_>>());
+    assert_eq!(column_count, 2);
+    assert_eq!(cells.last().map(|cell| cell.colspan), Some(2));
+    let rows = arrange(&table, Bounds::new(0.0, 0.0, 180.0, 120.0), &mut MeasureCache::new());
+    assert_eq!(rows[3].cells[0].1.width, 180.0);
+}
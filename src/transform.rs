@@ -0,0 +1,165 @@
+//! 2D affine transforms
+//!
+//! Uses the same convention as the CSS `matrix(a, b, c, d, e, f)` function:
+//!
+//! ```text
+//! x' = a * x + c * y + e
+//! y' = b * x + d * y + f
+//! ```
+//!
+//! Angles are in degrees and positive angles rotate clockwise on the screen
+//! (y points down), the same as `Rect::rotate_center`.
+
+/// A 2D affine transform (translation, rotation, scale, skew)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform2D {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform2D {
+    fn default()
+    -> Self
+    {
+        Self::identity()
+    }
+}
+
+impl Transform2D {
+
+    /// Creates a transform from the six matrix values
+    #[inline]
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32)
+    -> Self
+    {
+        Self { a, b, c, d, e, f }
+    }
+
+    /// The transform that doesn't change anything
+    #[inline]
+    pub fn identity()
+    -> Self
+    {
+        Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Moves by (x, y)
+    #[inline]
+    pub fn translation(x: f32, y: f32)
+    -> Self
+    {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// Rotates around the origin
+    #[inline]
+    pub fn rotation(angle: f32)
+    -> Self
+    {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self::new(c, s, -s, c, 0.0, 0.0)
+    }
+
+    /// Scales away from the origin
+    #[inline]
+    pub fn scale(x: f32, y: f32)
+    -> Self
+    {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Skews along the x axis by `angle_x` and along the y axis by `angle_y`
+    #[inline]
+    pub fn skew(angle_x: f32, angle_y: f32)
+    -> Self
+    {
+        Self::new(1.0, angle_y.to_radians().tan(), angle_x.to_radians().tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// Returns the transform that first applies `self`, then `other`
+    pub fn then(&self, other: &Transform2D)
+    -> Self
+    {
+        Self::new(
+            other.a * self.a + other.c * self.b,
+            other.b * self.a + other.d * self.b,
+            other.a * self.c + other.c * self.d,
+            other.b * self.c + other.d * self.d,
+            other.a * self.e + other.c * self.f + other.e,
+            other.b * self.e + other.d * self.f + other.f,
+        )
+    }
+
+    /// Returns the same transform, but applied around (x, y) instead of the origin
+    /// (i.e. rotating around the center of a node)
+    #[inline]
+    pub fn around(&self, x: f32, y: f32)
+    -> Self
+    {
+        Self::translation(-x, -y).then(self).then(&Self::translation(x, y))
+    }
+
+    /// Determinant of the linear part, zero if the transform squashes everything onto a line
+    #[inline]
+    pub fn determinant(&self)
+    -> f32
+    {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Returns the transform that undoes this transform, if there is one
+    pub fn inverse(&self)
+    -> Option<Self>
+    {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        Some(Self::new(
+            self.d * inv_det,
+            -self.b * inv_det,
+            -self.c * inv_det,
+            self.a * inv_det,
+            (self.c * self.f - self.d * self.e) * inv_det,
+            (self.b * self.e - self.a * self.f) * inv_det,
+        ))
+    }
+
+    /// Returns if the transform doesn't change anything
+    #[inline]
+    pub fn is_identity(&self)
+    -> bool
+    {
+        *self == Self::identity()
+    }
+
+    /// Transforms a point
+    #[inline]
+    pub fn transform_point(&self, x: f32, y: f32)
+    -> (f32, f32)
+    {
+        (self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f)
+    }
+}
+
+#[test]
+fn transform_composition_and_inverse() {
+    let transform = Transform2D::rotation(90.0)
+        .then(&Transform2D::scale(2.0, 3.0))
+        .then(&Transform2D::translation(10.0, 20.0));
+
+    // (1, 0) -> rotated (0, 1) -> scaled (0, 3) -> translated (10, 23)
+    let (x, y) = transform.transform_point(1.0, 0.0);
+    assert!((x - 10.0).abs() < 0.0001 && (y - 23.0).abs() < 0.0001);
+
+    let (x, y) = transform.inverse().unwrap().transform_point(x, y);
+    assert!((x - 1.0).abs() < 0.0001 && y.abs() < 0.0001);
+
+    assert!(Transform2D::scale(0.0, 1.0).inverse().is_none());
+}
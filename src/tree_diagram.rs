@@ -0,0 +1,311 @@
+//! Tree diagram layout (Reingold-Tilford) for org charts, dependency trees, etc.
+//!
+//! Unlike the other layout modes, `LayoutMode::TreeDiagram` doesn't divide the
+//! space of a node between its children - it positions all descendants of the
+//! node as a tidy tree: parents are centered over their children, subtrees are
+//! pushed apart just far enough that they don't overlap on any level. Every
+//! child of the diagram node is the root of a tree, the trees are placed next to
+//! each other. Each node is as large as its `width` / `height` (or `min_width` /
+//! `min_height`), the layout modes of the descendants are ignored.
+
+use tree_node::TreeNode;
+use node_data::{NodeData, TreeDiagramLayout, TreeOrientation};
+use rect::Bounds;
+use layout_result::LayoutResults;
+use intrinsic::Axis;
+
+/// A straight line between a parent and a child
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LineSegment {
+    /// Start point (x, y), on the edge of the parent
+    pub start: (f32, f32),
+    /// End point (x, y), on the edge of the child
+    pub end: (f32, f32),
+}
+
+/// A node of the diagram and where it ends up, with its children in the diagram
+#[derive(Debug)]
+pub struct DiagramNode<N> {
+    /// The node
+    pub node: N,
+    /// Position and size of the node
+    pub bounds: Bounds,
+    /// The children of the node, in tree order
+    pub children: Vec<DiagramNode<N>>,
+}
+
+/// Leftmost and rightmost extent of a subtree on each level
+type Contour = Vec<(f32, f32)>;
+
+/// A laid out subtree, all offsets are relative to the center of the subtree root
+struct Subtree<N> {
+    node: N,
+    /// Size of the node along the sibling axis
+    breadth: f32,
+    /// Children with the offset of their center from the center of this node
+    children: Vec<(f32, Subtree<N>)>,
+    /// Extent of the subtree on each level, starting with the level of this node
+    contour: Contour,
+}
+
+/// Calculates the position of all descendants of a tree diagram node. The top
+/// left corner of the diagram is at the top left corner of `bounds`.
+/// Returns the roots of the trees (the children of `container`).
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(container: &N, bounds: Bounds, layout: &TreeDiagramLayout)
+-> Vec<DiagramNode<N>>
+{
+    let level_offsets = level_offsets(container, layout);
+    let (roots, contour) = layout_forest(container, layout);
+    let leftmost = contour.iter().map(|&(left, _)| left).fold(0.0, f32::min);
+
+    roots.into_iter().map(|(offset, root)| {
+        emit(root, offset - leftmost, 0, &level_offsets, layout.orientation, bounds)
+    }).collect()
+}
+
+/// Content size of a tree diagram node on one axis: the size of the whole diagram
+pub fn content_size<T, N: TreeNode<Data = NodeData<T>>>(container: &N, layout: &TreeDiagramLayout, axis: Axis)
+-> f32
+{
+    let (_, contour) = layout_forest(container, layout);
+    let breadth = contour.iter().map(|&(_, right)| right).fold(0.0, f32::max) -
+                  contour.iter().map(|&(left, _)| left).fold(0.0, f32::min);
+
+    let mut level_depths = Vec::new();
+    for root in container.children() {
+        collect_level_depths(&root, 0, layout.orientation, &mut level_depths);
+    }
+    let depth = level_depths.iter().sum::<f32>() +
+                layout.level_separation * level_depths.len().saturating_sub(1) as f32;
+
+    match (layout.orientation, axis) {
+        (TreeOrientation::TopDown, Axis::Horizontal) | (TreeOrientation::LeftRight, Axis::Vertical) => breadth,
+        _ => depth,
+    }
+}
+
+/// Lines from every node of the diagram to its children, calculated from the
+/// layout of the descendants of `container` (call this after the layout), in tree
+/// order. The lines are in layout coordinates, before any transforms.
+pub fn connectors<T, N: TreeNode<Data = NodeData<T>>>(container: &N, results: &LayoutResults, layout: &TreeDiagramLayout)
+-> Vec<LineSegment>
+{
+    let mut connectors = Vec::new();
+    for root in container.children() {
+        collect_connectors(&root, results, layout.orientation, &mut connectors);
+    }
+    connectors
+}
+
+/// Returns the (breadth, depth) size of a node for the given orientation
+fn node_size<T, N: TreeNode<Data = NodeData<T>>>(node: &N, orientation: TreeOrientation)
+-> (f32, f32)
+{
+    let data = node.borrow();
+    let width = data.width.or(data.min_width).unwrap_or(0.0);
+    let height = data.height.or(data.min_height).unwrap_or(0.0);
+    match orientation {
+        TreeOrientation::TopDown => (width, height),
+        TreeOrientation::LeftRight => (height, width),
+    }
+}
+
+/// Offset of each level from the top (or left) edge of the diagram
+fn level_offsets<T, N: TreeNode<Data = NodeData<T>>>(container: &N, layout: &TreeDiagramLayout)
+-> Vec<f32>
+{
+    let mut level_depths = Vec::new();
+    for root in container.children() {
+        collect_level_depths(&root, 0, layout.orientation, &mut level_depths);
+    }
+
+    let mut level_offsets = Vec::with_capacity(level_depths.len());
+    let mut offset = 0.0;
+    for depth in &level_depths {
+        level_offsets.push(offset);
+        offset += depth + layout.level_separation;
+    }
+    level_offsets
+}
+
+/// Finds the largest depth of the nodes on each level, so that levels don't overlap
+fn collect_level_depths<T, N: TreeNode<Data = NodeData<T>>>(node: &N, level: usize,
+                                                            orientation: TreeOrientation, level_depths: &mut Vec<f32>)
+{
+    let (_, depth) = node_size(node, orientation);
+    if level_depths.len() <= level {
+        level_depths.push(depth);
+    } else if depth > level_depths[level] {
+        level_depths[level] = depth;
+    }
+
+    for child in node.children() {
+        collect_level_depths(&child, level + 1, orientation, level_depths);
+    }
+}
+
+/// Lays out the children of `parent` from left to right, each child subtree is moved right
+/// until it doesn't overlap the previous subtrees. Returns the subtrees with their offset
+/// from the center of the first child, and the merged contour of all subtrees.
+fn layout_forest<T, N: TreeNode<Data = NodeData<T>>>(parent: &N, layout: &TreeDiagramLayout)
+-> (Vec<(f32, Subtree<N>)>, Contour)
+{
+    let mut children = Vec::<(f32, Subtree<N>)>::new();
+    let mut children_contour = Contour::new();
+
+    for child in parent.children() {
+        let child_subtree = layout_subtree(&child, layout);
+
+        let offset = if children.is_empty() {
+            0.0
+        } else {
+            children_contour.iter().zip(child_subtree.contour.iter())
+                .map(|(&(_, right), &(left, _))| right - left + layout.sibling_separation)
+                .fold(f32::MIN, f32::max)
+        };
+
+        for (level, &(left, right)) in child_subtree.contour.iter().enumerate() {
+            if level < children_contour.len() {
+                let merged = &mut children_contour[level];
+                merged.0 = merged.0.min(left + offset);
+                merged.1 = merged.1.max(right + offset);
+            } else {
+                children_contour.push((left + offset, right + offset));
+            }
+        }
+
+        children.push((offset, child_subtree));
+    }
+
+    (children, children_contour)
+}
+
+/// Lays out the children of the node, then centers the node over them
+fn layout_subtree<T, N: TreeNode<Data = NodeData<T>>>(node: &N, layout: &TreeDiagramLayout)
+-> Subtree<N>
+{
+    let (breadth, _) = node_size(node, layout.orientation);
+    let (mut children, children_contour) = layout_forest(node, layout);
+
+    // center the parent over its first and last child
+    let center = match (children.first(), children.last()) {
+        (Some(first), Some(last)) => (first.0 + last.0) * 0.5,
+        _ => 0.0,
+    };
+
+    for child in &mut children {
+        child.0 -= center;
+    }
+
+    let mut contour = vec![(-breadth * 0.5, breadth * 0.5)];
+    contour.extend(children_contour.into_iter().map(|(left, right)| (left - center, right - center)));
+
+    Subtree {
+        node: node.clone(),
+        breadth,
+        children,
+        contour,
+    }
+}
+
+/// Converts the relative offsets into bounds, `center` is relative to the diagram
+fn emit<T, N: TreeNode<Data = NodeData<T>>>(subtree: Subtree<N>, center: f32, level: usize,
+                                            level_offsets: &[f32], orientation: TreeOrientation,
+                                            diagram_bounds: Bounds)
+-> DiagramNode<N>
+{
+    let (_, depth) = node_size(&subtree.node, orientation);
+    let start = center - subtree.breadth * 0.5;
+    let level_offset = level_offsets[level];
+
+    let bounds = match orientation {
+        TreeOrientation::TopDown => Bounds::new(diagram_bounds.left + start, diagram_bounds.top + level_offset,
+                                                subtree.breadth, depth),
+        TreeOrientation::LeftRight => Bounds::new(diagram_bounds.left + level_offset, diagram_bounds.top + start,
+                                                  depth, subtree.breadth),
+    };
+
+    let children = subtree.children.into_iter().map(|(offset, child)| {
+        emit(child, center + offset, level + 1, level_offsets, orientation, diagram_bounds)
+    }).collect();
+
+    DiagramNode { node: subtree.node, bounds, children }
+}
+
+/// Adds the lines from the node to its children, then the lines of the children
+fn collect_connectors<N: TreeNode>(node: &N, results: &LayoutResults, orientation: TreeOrientation,
+                                   connectors: &mut Vec<LineSegment>)
+{
+    let parent = match results.get(node) {
+        Some(result) => result.bounds,
+        None => return,
+    };
+
+    for child in node.children() {
+        if let Some(child_bounds) = results.get(&child).map(|result| result.bounds) {
+            let (start, end) = match orientation {
+                TreeOrientation::TopDown => ((parent.left + parent.width * 0.5, parent.bottom()),
+                                             (child_bounds.left + child_bounds.width * 0.5, child_bounds.top)),
+                TreeOrientation::LeftRight => ((parent.right(), parent.top + parent.height * 0.5),
+                                               (child_bounds.left, child_bounds.top + child_bounds.height * 0.5)),
+            };
+            connectors.push(LineSegment { start, end });
+        }
+        collect_connectors(&child, results, orientation, connectors);
+    }
+}
+
+#[test]
+fn tree_diagram_centers_parent_over_children() {
+    use rctree::NodeRef;
+    use node_data::{FlexDirection, LayoutMode};
+    use ui_screen::UiScreen;
+    use intrinsic::{max_content_width, max_content_height};
+
+    let node = |width: f32, data: usize| {
+        let mut node_data = NodeData::empty(FlexDirection::Row, data);
+        node_data.width = Some(width);
+        node_data.height = Some(10.0);
+        NodeRef::new(node_data)
+    };
+
+    let layout = TreeDiagramLayout::default();
+    let mut ui_screen = UiScreen::new(200.0, 100.0, NodeData::empty(FlexDirection::Row, 0)
+                                                        .with_layout_mode(LayoutMode::TreeDiagram(layout)));
+    let root = node(20.0, 1);
+    let left = node(40.0, 2);
+    left.append(node(10.0, 3));
+    left.append(node(10.0, 4));
+    root.append(left);
+    // the layout mode of a diagram node is ignored
+    let right = node(40.0, 5);
+    right.borrow_mut().layout_mode = LayoutMode::Table;
+    root.append(right);
+    ui_screen.root.append(root.clone());
+    ui_screen.compute_layout(200.0, 100.0);
+
+    // root, left, left's children, right
+    let nodes = root.descendants().collect::<Vec<_>>();
+    let centers = nodes.iter().map(|node| {
+        let bounds = ui_screen.layout(node).unwrap().bounds;
+        bounds.left + bounds.width * 0.5
+    }).collect::<Vec<_>>();
+    assert_eq!(centers, vec![45.0, 20.0, 10.0, 30.0, 70.0]);
+    assert_eq!(ui_screen.layout(&nodes[2]).unwrap().bounds.top, 60.0);
+
+    // every node is drawn inside of the z-range of its parent in the diagram
+    let z = |index: usize| ui_screen.layout(&nodes[index]).unwrap().z;
+    assert!(ui_screen.layout(&ui_screen.root).unwrap().z < z(0));
+    assert!(z(0) < z(1) && z(1) < z(2) && z(2) < z(3) && z(3) < z(4) && z(4) < 1.0);
+
+    let connectors = connectors(&ui_screen.root, ui_screen.layout_results(), &layout);
+    assert_eq!(connectors.len(), 4);
+    assert_eq!(connectors[0], LineSegment { start: (45.0, 10.0), end: (20.0, 30.0) });
+
+    // the diagram is as large as its widest level and all levels
+    let container = NodeRef::new(NodeData::empty(FlexDirection::Row, 0).with_layout_mode(LayoutMode::TreeDiagram(layout)));
+    root.detach();
+    container.append(root);
+    assert_eq!(max_content_width(&container), 90.0);
+    assert_eq!(max_content_height(&container), 10.0 * 3.0 + 20.0 * 2.0);
+}
@@ -0,0 +1,215 @@
+//! Traits that the layout uses to walk a tree, so that the same layout code works
+//! on the reference counted `rctree::NodeRef` and on the index based `arena::Arena`
+//!
+//! `TreeNode` is a handle to a single node (navigation and read access),
+//! `LayoutTree` is the owner of a whole tree that a `UiScreen` is built on.
+
+use std::ops::Deref;
+use rctree::{self, NodeRef};
+use node_data::NodeData;
+
+/// Anything that identifies a node, see `NodeRef::node_id`
+pub trait HasNodeId {
+    /// Number that identifies the node, only unique as long as the node is alive
+    fn node_id(&self) -> u64;
+}
+
+/// A handle to a node of a tree, with read access to the data of the node
+pub trait TreeNode: HasNodeId + Clone + Sized {
+    /// Data stored in every node
+    type Data;
+    /// Owned reference to the node that can outlive the borrow of the tree
+    /// (i.e. for storing it in the output of the layout)
+    type Handle: Clone;
+    /// Shared reference to the data of the node
+    type Ref<'b>: Deref<Target = Self::Data> where Self: 'b;
+
+    /// Returns a shared reference to the data of the node
+    fn borrow(&self) -> Self::Ref<'_>;
+    /// Returns the parent of the node, unless this node is the root of the tree
+    fn parent(&self) -> Option<Self>;
+    /// Returns the first child of the node, unless it has no child
+    fn first_child(&self) -> Option<Self>;
+    /// Returns the last child of the node, unless it has no child
+    fn last_child(&self) -> Option<Self>;
+    /// Returns the previous sibling of the node, unless it is a first child
+    fn previous_sibling(&self) -> Option<Self>;
+    /// Returns the next sibling of the node, unless it is a last child
+    fn next_sibling(&self) -> Option<Self>;
+    /// Returns whether the node or one of its descendants changed since the last layout
+    fn is_dirty(&self) -> bool;
+    /// Returns an owned reference to the node
+    fn handle(&self) -> Self::Handle;
+
+    /// Returns an iterator of this node and its ancestors
+    #[inline]
+    fn ancestors(&self) -> Ancestors<Self> {
+        Ancestors(Some(self.clone()))
+    }
+
+    /// Returns an iterator of the children of this node
+    #[inline]
+    fn children(&self) -> Children<Self> {
+        Children(self.first_child())
+    }
+
+    /// Returns an iterator of the children of this node, in reverse order
+    #[inline]
+    fn reverse_children(&self) -> ReverseChildren<Self> {
+        ReverseChildren(self.last_child())
+    }
+
+    /// Returns an iterator of this node and its descendants, in tree order
+    #[inline]
+    fn descendants(&self) -> Descendants<Self> {
+        Descendants { root: self.node_id(), next: Some(self.clone()) }
+    }
+}
+
+macro_rules! impl_tree_node_iterator {
+    ($name: ident, $next: ident) => {
+        impl<N: TreeNode> Iterator for $name<N> {
+            type Item = N;
+
+            fn next(&mut self) -> Option<N> {
+                let node = self.0.take()?;
+                self.0 = node.$next();
+                Some(node)
+            }
+        }
+    }
+}
+
+/// An iterator of a node and its ancestors
+pub struct Ancestors<N>(Option<N>);
+impl_tree_node_iterator!(Ancestors, parent);
+
+/// An iterator of the children of a node
+pub struct Children<N>(Option<N>);
+impl_tree_node_iterator!(Children, next_sibling);
+
+/// An iterator of the children of a node, in reverse order
+pub struct ReverseChildren<N>(Option<N>);
+impl_tree_node_iterator!(ReverseChildren, previous_sibling);
+
+/// An iterator of a node and its descendants, in tree order
+pub struct Descendants<N> {
+    root: u64,
+    next: Option<N>,
+}
+
+impl<N: TreeNode> Iterator for Descendants<N> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        let node = self.next.take()?;
+
+        self.next = node.first_child().or_else(|| {
+            // go up until there is a next sibling, but not past the root
+            let mut current = node.clone();
+            loop {
+                if current.node_id() == self.root {
+                    return None;
+                }
+                if let Some(next_sibling) = current.next_sibling() {
+                    return Some(next_sibling);
+                }
+                current = current.parent()?;
+            }
+        });
+
+        Some(node)
+    }
+}
+
+impl<T> HasNodeId for NodeRef<T> {
+    #[inline]
+    fn node_id(&self) -> u64 {
+        NodeRef::node_id(self) as u64
+    }
+}
+
+impl<T> TreeNode for NodeRef<T> {
+    type Data = T;
+    type Handle = NodeRef<T>;
+    type Ref<'b> = rctree::Ref<'b, T> where Self: 'b;
+
+    #[inline]
+    fn borrow(&self) -> rctree::Ref<'_, T> {
+        NodeRef::borrow(self)
+    }
+
+    #[inline]
+    fn parent(&self) -> Option<Self> {
+        NodeRef::parent(self)
+    }
+
+    #[inline]
+    fn first_child(&self) -> Option<Self> {
+        NodeRef::first_child(self)
+    }
+
+    #[inline]
+    fn last_child(&self) -> Option<Self> {
+        NodeRef::last_child(self)
+    }
+
+    #[inline]
+    fn previous_sibling(&self) -> Option<Self> {
+        NodeRef::previous_sibling(self)
+    }
+
+    #[inline]
+    fn next_sibling(&self) -> Option<Self> {
+        NodeRef::next_sibling(self)
+    }
+
+    #[inline]
+    fn is_dirty(&self) -> bool {
+        NodeRef::is_dirty(self)
+    }
+
+    #[inline]
+    fn handle(&self) -> NodeRef<T> {
+        self.clone()
+    }
+}
+
+/// A tree of `NodeData` that a `UiScreen` can be built on
+pub trait LayoutTree {
+    /// User data of the nodes (`NodeData::data`)
+    type Data;
+    /// Owned reference to a node, see `TreeNode::Handle`
+    type Handle: Clone;
+    /// Handle to a node, borrowing the tree
+    type Node<'a>: TreeNode<Data = NodeData<Self::Data>, Handle = Self::Handle> where Self: 'a;
+
+    /// Returns the root node of the tree
+    fn root_node(&self) -> Self::Node<'_>;
+    /// Changes the data of the root node (marks the root as dirty)
+    fn update_root<F>(&mut self, f: F) where F: FnOnce(&mut NodeData<Self::Data>);
+    /// Clears the dirty flags of all nodes, called after the tree was laid out
+    fn mark_clean(&mut self);
+}
+
+/// A `NodeRef` owns the tree below it, the root node is the tree
+impl<T> LayoutTree for NodeRef<NodeData<T>> {
+    type Data = T;
+    type Handle = NodeRef<NodeData<T>>;
+    type Node<'a> = NodeRef<NodeData<T>> where Self: 'a;
+
+    #[inline]
+    fn root_node(&self) -> NodeRef<NodeData<T>> {
+        self.clone()
+    }
+
+    #[inline]
+    fn update_root<F>(&mut self, f: F) where F: FnOnce(&mut NodeData<T>) {
+        f(&mut self.borrow_mut());
+    }
+
+    #[inline]
+    fn mark_clean(&mut self) {
+        NodeRef::mark_clean(self);
+    }
+}
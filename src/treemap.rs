@@ -0,0 +1,155 @@
+//! Squarified treemap layout (Bruls, Huizing, van Wijk)
+//!
+//! The space of the container is divided between the children proportionally
+//! to their weight. Children are added to a strip along the shorter side of
+//! the remaining space for as long as this improves the worst aspect ratio in
+//! the strip, then a new strip is started in the space that is left.
+
+use tree_node::TreeNode;
+use node_data::{NodeData, TreemapLayout};
+use rect::Bounds;
+
+/// Weight of a node: its own `weight`, or the sum of the weights of its children
+pub fn node_weight<T, N: TreeNode<Data = NodeData<T>>>(node: &N)
+-> f32
+{
+    match node.borrow().weight {
+        Some(weight) => weight.max(0.0),
+        None => node.children().map(|child| node_weight(&child)).sum(),
+    }
+}
+
+/// Calculates the position of the children of a treemap container inside of `bounds`.
+/// The children are returned in tree order.
+pub fn arrange<T, N: TreeNode<Data = NodeData<T>>>(container: &N, bounds: Bounds, layout: &TreemapLayout)
+-> Vec<(N, Bounds)>
+{
+    let children = container.children().collect::<Vec<_>>();
+    let weights = children.iter().map(node_weight).collect::<Vec<_>>();
+    let boxes = squarify(&weights, inset(bounds, layout.padding));
+    children.into_iter().zip(boxes).collect()
+}
+
+/// Divides `bounds` into one box per weight, the area of each box is
+/// proportional to its weight. The boxes are returned in the order of `weights`.
+pub fn squarify(weights: &[f32], bounds: Bounds)
+-> Vec<Bounds>
+{
+    let empty = Bounds::new(bounds.left, bounds.top, 0.0, 0.0);
+    let mut boxes = vec![empty; weights.len()];
+
+    // zero, negative and NaN weights stay empty
+    let total_weight: f32 = weights.iter().filter(|&&weight| weight > 0.0).sum();
+    if total_weight <= 0.0 || bounds.width <= 0.0 || bounds.height <= 0.0 {
+        return boxes;
+    }
+
+    // largest first
+    let scale = bounds.width * bounds.height / total_weight;
+    let mut order = (0..weights.len()).filter(|&i| weights[i] > 0.0).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| weights[b].total_cmp(&weights[a]));
+
+    let mut remaining = bounds;
+    let mut strip = Vec::<usize>::new();
+    let mut strip_area = 0.0;
+
+    for index in order {
+        let area = weights[index] * scale;
+        let side = remaining.width.min(remaining.height);
+
+        let current_worst = worst_ratio(&strip, strip_area, side, weights, scale);
+        strip.push(index);
+        let new_worst = worst_ratio(&strip, strip_area + area, side, weights, scale);
+
+        if strip.len() > 1 && new_worst > current_worst {
+            strip.pop();
+            remaining = lay_out_strip(&strip, strip_area, remaining, weights, scale, &mut boxes);
+            strip.clear();
+            strip.push(index);
+            strip_area = area;
+        } else {
+            strip_area += area;
+        }
+    }
+
+    if !strip.is_empty() {
+        lay_out_strip(&strip, strip_area, remaining, weights, scale, &mut boxes);
+    }
+
+    boxes
+}
+
+/// Worst (largest) aspect ratio of the boxes in a strip along a side of length `side`
+fn worst_ratio(strip: &[usize], strip_area: f32, side: f32, weights: &[f32], scale: f32)
+-> f32
+{
+    if strip.is_empty() || strip_area <= 0.0 {
+        return f32::MAX;
+    }
+
+    let side_squared = side * side;
+    let area_squared = strip_area * strip_area;
+
+    strip.iter().map(|&index| {
+        let area = weights[index] * scale;
+        (side_squared * area / area_squared).max(area_squared / (side_squared * area))
+    }).fold(0.0, f32::max)
+}
+
+/// Places the strip along the shorter side of `remaining`, returns the space that is left
+fn lay_out_strip(strip: &[usize], strip_area: f32, remaining: Bounds,
+                 weights: &[f32], scale: f32, boxes: &mut [Bounds])
+-> Bounds
+{
+    if remaining.width >= remaining.height {
+        // vertical strip on the left
+        let strip_width = if remaining.height > 0.0 { strip_area / remaining.height } else { 0.0 };
+        let mut top = remaining.top;
+        for &index in strip {
+            let height = if strip_width > 0.0 { weights[index] * scale / strip_width } else { 0.0 };
+            boxes[index] = Bounds::new(remaining.left, top, strip_width, height);
+            top += height;
+        }
+        Bounds::new(remaining.left + strip_width, remaining.top, remaining.width - strip_width, remaining.height)
+    } else {
+        // horizontal strip at the top
+        let strip_height = if remaining.width > 0.0 { strip_area / remaining.width } else { 0.0 };
+        let mut left = remaining.left;
+        for &index in strip {
+            let width = if strip_height > 0.0 { weights[index] * scale / strip_height } else { 0.0 };
+            boxes[index] = Bounds::new(left, remaining.top, width, strip_height);
+            left += width;
+        }
+        Bounds::new(remaining.left, remaining.top + strip_height, remaining.width, remaining.height - strip_height)
+    }
+}
+
+/// Shrinks the box by `padding` on each side
+fn inset(bounds: Bounds, padding: f32)
+-> Bounds
+{
+    Bounds::new(bounds.left + padding, bounds.top + padding,
+                (bounds.width - padding * 2.0).max(0.0), (bounds.height - padding * 2.0).max(0.0))
+}
+
+#[test]
+fn squarify_areas_are_proportional_to_weights() {
+    // example from the paper
+    let weights = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+    let boxes = squarify(&weights, Bounds::new(0.0, 0.0, 6.0, 4.0));
+
+    for (weight, b) in weights.iter().zip(boxes.iter()) {
+        assert!((b.width * b.height - weight).abs() < 0.001);
+        assert!(b.left >= 0.0 && b.top >= 0.0 && b.right() <= 6.001 && b.bottom() <= 4.001);
+    }
+
+    // the first strip holds the two largest items on the left
+    assert_eq!(boxes[0], Bounds::new(0.0, 0.0, 3.0, 2.0));
+    assert_eq!(boxes[1], Bounds::new(0.0, 2.0, 3.0, 2.0));
+
+    // invalid weights from user data stay empty instead of panicking
+    let boxes = squarify(&[f32::NAN, 1.0, -1.0], Bounds::new(0.0, 0.0, 6.0, 4.0));
+    assert_eq!(boxes[0].width * boxes[0].height, 0.0);
+    assert_eq!(boxes[1], Bounds::new(0.0, 0.0, 6.0, 4.0));
+    assert_eq!(boxes[2].width * boxes[2].height, 0.0);
+}
@@ -1,85 +1,429 @@
 //! Ui screen is a single screen that is visible at one time
-//! Uses rctree for reference counted nodes
+//! Uses rctree for reference counted nodes, or an arena (see `arena::ArenaTree`)
 
 use rctree::NodeRef;
-use node_data::{NodeData, FlexDirection};
-use rect::Rect;
+use arena::ArenaTree;
+use tree_node::{HasNodeId, TreeNode, LayoutTree};
+use node_data::{NodeData, FlexDirection, AlignItems, LayoutMode, Overflow};
+use rect::{Rect, Bounds};
+use layout_result::{LayoutResult, LayoutResults, LayoutRecord, LayoutKey};
+use transform::Transform2D;
+use shared_size::SharedSizes;
+use baseline::align_children_to_baseline;
+use table;
+use inline_flow;
+use masonry;
+use treemap;
+use radial;
+use tree_diagram::{self, DiagramNode};
+use popover::{PopoverOptions, PopoverPlacement, place_popover};
+use intrinsic::{max_content_width, max_content_height};
+use measure_cache::MeasureCache;
+use hit_test::{HitTestResult, hit_test};
+use damage::LayoutSnapshot;
+use std::{mem, panic, thread};
+use std::marker::PhantomData;
 
 /// UI screen
+///
+/// The tree is a `NodeRef` (the root node) by default, any other `LayoutTree`
+/// (i.e. an `arena::ArenaTree`) can be used via `from_tree`. `NodeRef` is built
+/// on `Rc`, so only a screen on an `ArenaTree` can be sent to another thread.
 #[derive(Debug)]
-pub struct UiScreen<T: Clone> {
-    /// Root node of the UI tree
-    pub root: NodeRef<Rect<T>>,
+pub struct UiScreen<T, R = NodeRef<NodeData<T>>> {
+    /// Root node of the UI tree (or the tree that contains the root node)
+    pub root: R,
+    /// Computed layout of the nodes, from the last call to `into_rectangles`
+    results: LayoutResults,
+    /// Whether the last layout had shared size groups, see `compute_layout`
+    had_shared_sizes: bool,
+    /// Number of nodes that were laid out by the last layout
+    laid_out_count: usize,
+    /// Measured content sizes, kept between layouts for the nodes that didn't change
+    measure_cache: MeasureCache,
+    _data: PhantomData<T>,
 }
 
-impl<T: Clone> UiScreen<T> {
+/// Settings for laying out large sibling subtrees on multiple threads,
+/// see `UiScreen::compute_layout_parallel`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParallelLayout {
+    /// Maximum number of threads (including the calling thread)
+    pub threads: usize,
+    /// Number of nodes a subtree needs to be laid out on its own thread,
+    /// spawning a thread for a small subtree is slower than laying it out
+    pub min_subtree_size: usize,
+}
+
+impl Default for ParallelLayout {
+    fn default()
+    -> Self
+    {
+        Self {
+            threads: thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+            min_subtree_size: 256,
+        }
+    }
+}
+
+/// State that is passed through a single layout pass
+struct LayoutContext<'a, S> {
+    /// Resolved sizes of the shared size groups (empty on the first pass)
+    shared_sizes: &'a SharedSizes,
+    /// Results of the previous layout, clean subtrees are moved from here to `results`
+    previous: LayoutResults,
+    /// Receives the computed layout of every node
+    results: LayoutResults,
+    /// Content sizes of the nodes, shared by all passes
+    measure_cache: &'a mut MeasureCache,
+    /// Number of nodes that were laid out (not reused)
+    laid_out_count: usize,
+    /// Places the children of a node, see `PlaceSubtrees`
+    strategy: S,
+}
+
+impl<T> UiScreen<T> {
 
     /// Creates a new UiScreen
     #[inline]
     pub fn new(initial_width: f32, initial_height: f32, data: NodeData<T>)
     -> Self
     {
+        Self::from_tree(initial_width, initial_height, NodeRef::new(data))
+    }
+}
+
+impl<T> UiScreen<T, ArenaTree<T>> {
+
+    /// Creates a new UiScreen on an `ArenaTree`. Unlike the default tree, the
+    /// arena is `Send` and `Sync` (if `T` is), so the screen can be built and laid
+    /// out on a worker thread and the records sent to the render thread.
+    #[inline]
+    pub fn new_in_arena(initial_width: f32, initial_height: f32, data: NodeData<T>)
+    -> Self
+    {
+        Self::from_tree(initial_width, initial_height, ArenaTree::new(data))
+    }
+}
+
+impl<T: Sync> UiScreen<T, ArenaTree<T>> {
+
+    /// Same as `compute_layout`, but sibling subtrees with at least
+    /// `options.min_subtree_size` nodes are laid out on multiple threads.
+    /// The results can be queried with `records` / `layout_results`.
+    pub fn compute_layout_parallel(&mut self, root_width: f32, root_height: f32, options: &ParallelLayout)
+    {
+        self.compute_layout_with(root_width, root_height, |tree, previous, measure_cache| {
+            layout_tree(&tree.root_node(), previous, measure_cache, *options)
+        });
+    }
+}
+
+impl<T, R: LayoutTree<Data = T>> UiScreen<T, R> {
+
+    /// Creates a new UiScreen from an existing tree
+    pub fn from_tree(initial_width: f32, initial_height: f32, tree: R)
+    -> Self
+    {
+        let mut tree = tree;
+        tree.update_root(|root| {
+            root.width = Some(initial_width);
+            root.height = Some(initial_height);
+        });
+
         Self {
-            root: NodeRef::new(Rect::new(0.0, initial_height, 0.0, initial_width, 0.0, data))
+            root: tree,
+            results: LayoutResults::new(),
+            had_shared_sizes: false,
+            laid_out_count: 0,
+            measure_cache: MeasureCache::new(),
+            _data: PhantomData,
         }
     }
 
     /// Changes the default orientation for the root element from row to column
     #[inline]
-    pub fn with_root_as_column(self)
+    pub fn with_root_as_column(mut self)
     -> Self
     {
-        self.root.borrow_mut().data.flex_direction = FlexDirection::Column;
+        self.root.update_root(|root| root.flex_direction = FlexDirection::Column);
         self
     }
 
-    /// Converts the UI into a vertex buffer
+    /// Lays out the tree without generating any output, the results can be
+    /// queried with `layout` / `layout_results`
+    ///
+    /// Only dirty subtrees (see `NodeRef::is_dirty`) are laid out again, clean
+    /// subtrees that got the same size and z-range as in the last layout are
+    /// reused (and moved if necessary). If the tree has shared size groups, a
+    /// change anywhere can resize any member, so the whole tree is laid out again.
+    pub fn compute_layout(&mut self, root_width: f32, root_height: f32)
+    {
+        self.compute_layout_with(root_width, root_height, |tree, previous, measure_cache| {
+            layout_tree(&tree.root_node(), previous, measure_cache, Sequential)
+        });
+    }
+
+    /// See `compute_layout`, `layout` lays out the tree with `layout_tree`
+    fn compute_layout_with<F>(&mut self, root_width: f32, root_height: f32, layout: F)
+    where F: FnOnce(&R, LayoutResults, &mut MeasureCache) -> (LayoutResults, usize, bool)
+    {
+        // changing the root marks it as dirty
+        let size_changed = {
+            let root = self.root.root_node();
+            let data = root.borrow();
+            data.width != Some(root_width) || data.height != Some(root_height)
+        };
+        if size_changed {
+            self.root.update_root(|root| {
+                root.width = Some(root_width);
+                root.height = Some(root_height);
+            });
+        }
+
+        let previous = if self.had_shared_sizes {
+            LayoutResults::new()
+        } else {
+            mem::replace(&mut self.results, LayoutResults::new())
+        };
+        let mut measure_cache = mem::replace(&mut self.measure_cache, MeasureCache::new());
+
+        let (results, laid_out_count, has_shared_sizes) = layout(&self.root, previous, &mut measure_cache);
+        self.results = results;
+        self.had_shared_sizes = has_shared_sizes;
+        self.laid_out_count = laid_out_count;
+
+        self.measure_cache = measure_cache;
+        self.root.mark_clean();
+    }
+
+    /// Number of nodes that were laid out by the last `compute_layout`,
+    /// the layout of all other nodes was reused from the layout before
+    #[inline]
+    pub fn laid_out_count(&self)
+    -> usize
+    {
+        self.laid_out_count
+    }
+
+    /// Lays out the tree and returns a lightweight record for every node, in the
+    /// same order as `into_rectangles`. Unlike `into_rectangles`, this doesn't clone
+    /// the data of the nodes, so `T` doesn't have to implement `Clone`.
+    pub fn into_records(&mut self, root_width: f32, root_height: f32)
+    -> Vec<LayoutRecord<R::Handle>>
+    {
+        self.compute_layout(root_width, root_height);
+        self.records()
+    }
+
+    /// Records of the last layout, in the same order as `into_records`
+    pub fn records(&self)
+    -> Vec<LayoutRecord<R::Handle>>
+    {
+        let mut records = Vec::new();
+        visit_laid_out(&self.root.root_node(), &self.results, &mut |node, result| {
+            records.push(LayoutRecord { node: node.handle(), layout: *result });
+        });
+        records
+    }
+
+    /// Computed layout of a node (position, size, z-index, final corners),
+    /// `None` if the node wasn't laid out yet. Call this after `compute_layout`.
+    #[inline]
+    pub fn layout<K: HasNodeId>(&self, node: &K)
+    -> Option<&LayoutResult>
+    {
+        self.results.get(node)
+    }
+
+    /// Computed layout of all nodes
+    #[inline]
+    pub fn layout_results(&self)
+    -> &LayoutResults
+    {
+        &self.results
+    }
+
+    /// Returns the topmost node under the point (x, y) and its ancestors.
+    /// Call this after `into_rectangles`, see `hit_test::hit_test`.
+    #[inline]
+    pub fn hit_test(&self, x: f32, y: f32)
+    -> Option<HitTestResult<R::Handle>>
+    {
+        hit_test(&self.root.root_node(), &self.results, x, y)
+    }
+
+    /// Takes a snapshot of the current layout, for computing the damage
+    /// regions of the next layout, see `damage::damage_regions`
+    #[inline]
+    pub fn snapshot(&self)
+    -> LayoutSnapshot<R::Handle>
+    {
+        LayoutSnapshot::capture(&self.root.root_node(), &self.results)
+    }
+}
+
+impl<T: Clone, R: LayoutTree<Data = T>> UiScreen<T, R> {
+
+    /// Converts the UI into a vertex buffer. Every rectangle contains a clone of
+    /// the data of its node, use `into_records` if that is too expensive.
     pub fn into_rectangles(&mut self, root_width: f32, root_height: f32)
     -> Vec<Rect<T>>
     {
-        self.root.borrow_mut().data.width = Some(root_width);
-        self.root.borrow_mut().data.height = Some(root_height);
+        self.compute_layout(root_width, root_height);
+
+        let mut rectangles = Vec::<Rect<T>>::new();
+        collect_rectangles(&self.root.root_node(), &self.results, &mut rectangles);
+        rectangles
+    }
+
+    /// Lays out a floating node (dropdown, tooltip, ...) that is not part of the
+    /// tree next to the computed rectangle of `anchor`, flipping / shifting it so
+    /// that it stays on the screen. Call this after `into_rectangles`.
+    ///
+    /// The floating node is as large as its max-content size and is drawn above
+    /// all nodes of the tree (z-index between 1.0 and 2.0). Returns `None` if
+    /// the anchor wasn't laid out (i.e. it is not part of the tree).
+    pub fn layout_popover<K, N>(&self, anchor: &K, popover: &N, options: &PopoverOptions)
+    -> Option<(PopoverPlacement, Vec<Rect<T>>)>
+    where K: HasNodeId, N: TreeNode<Data = NodeData<T>>
+    {
+        let anchor_bounds = self.results.get(anchor)?.bounding_box();
+
+        let viewport = {
+            let root = self.root.root_node();
+            let data = root.borrow();
+            Bounds::new(0.0, 0.0, data.width.unwrap_or(0.0), data.height.unwrap_or(0.0))
+        };
+
+        let placement = place_popover(&anchor_bounds, max_content_width(popover), max_content_height(popover),
+                                      &viewport, options);
+        let (z, max_z) = z_index(1.0, 2.0, 1, 0);
+        let mut ctx = LayoutContext {
+            shared_sizes: &SharedSizes::default(),
+            previous: LayoutResults::new(),
+            results: LayoutResults::new(),
+            measure_cache: &mut MeasureCache::new(),
+            laid_out_count: 0,
+            strategy: Sequential,
+        };
+        place_node(popover, placement.bounds, z, max_z, None, &mut ctx);
+        place_on_screen(popover, &Transform2D::identity(), &mut ctx.results);
 
-        let mut cur_offset_width = 0.0;
-        let mut cur_offset_height = 0.0;
-        let parent_width = self.root.borrow().data.width.unwrap().clone();
-        let parent_height = self.root.borrow().data.height.unwrap().clone();
+        let mut rectangles = Vec::<Rect<T>>::new();
+        collect_rectangles(popover, &ctx.results, &mut rectangles);
+        Some((placement, rectangles))
+    }
+}
+
+/// Lays out the tree (one or two passes, see `UiScreen::compute_layout`) and
+/// places it on the screen. Returns the results, the number of nodes that were
+/// laid out and whether the tree has shared size groups.
+fn layout_tree<T, N, S>(root: &N, previous: LayoutResults, measure_cache: &mut MeasureCache, strategy: S)
+-> (LayoutResults, usize, bool)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
+    // measurements of dirty nodes are outdated
+    measure_cache.remove_dirty(root);
 
-        let min_z_index = 0.0;
-        let max_z_index = 1.0;
-        let root_sibling_count = 0;
-        let root_level_children = 1;
+    let (mut results, mut laid_out_count) = layout_pass(root, &SharedSizes::default(), previous, measure_cache, strategy);
 
-        ui_screen_to_dp_list::<T>(&self.root, min_z_index, max_z_index,
-                             root_level_children, root_sibling_count,
-                             parent_width, parent_height, 0.0, 0.0,
-                             &mut cur_offset_width, &mut cur_offset_height)
+    // nodes in a shared size group can only be sized once the size of
+    // every other member is known, so they need a second pass
+    let shared_sizes = SharedSizes::collect(root, &results);
+    if !shared_sizes.is_empty() {
+        let (second_results, second_count) = layout_pass(root, &shared_sizes, LayoutResults::new(), measure_cache, strategy);
+        results = second_results;
+        laid_out_count += second_count;
     }
+
+    // scrolling and transforms don't affect the layout, so they are applied
+    // to the whole tree afterwards, reused subtrees included
+    place_on_screen(root, &Transform2D::identity(), &mut results);
+    measure_cache.remove_detached(root);
+
+    (results, laid_out_count, !shared_sizes.is_empty())
 }
 
-/// Recursively traverse and convert the node data into a list of rectangles
+/// Lays out the whole tree once, starting from the root. Returns the results
+/// and the number of nodes that were laid out.
+fn layout_pass<T, N, S>(root: &N, shared_sizes: &SharedSizes, previous: LayoutResults,
+                        measure_cache: &mut MeasureCache, strategy: S)
+-> (LayoutResults, usize)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
+    let mut cur_offset_width = 0.0;
+    let mut cur_offset_height = 0.0;
+    let parent_width = root.borrow().width.unwrap();
+    let parent_height = root.borrow().height.unwrap();
+
+    let min_z_index = 0.0;
+    let max_z_index = 1.0;
+    let root_sibling_count = 0;
+    let root_level_children = 1;
+
+    let mut ctx = LayoutContext {
+        shared_sizes,
+        previous,
+        results: LayoutResults::new(),
+        measure_cache,
+        laid_out_count: 0,
+        strategy,
+    };
+
+    let bounds = ui_screen_to_dp_list(root, root_level_children, root_sibling_count,
+                                      parent_width, parent_height, 0.0, 0.0,
+                                      &mut cur_offset_width, &mut cur_offset_height,
+                                      shared_sizes);
+    let (z_index_root, max_z_root) = z_index(min_z_index, max_z_index, root_level_children, root_sibling_count);
+    place_node(root, bounds, z_index_root, max_z_root, None, &mut ctx);
+
+    (ctx.results, ctx.laid_out_count)
+}
+
+/// Calls `f` for the node and its descendants that were laid out, children before their parent
+fn visit_laid_out<N, F>(node: &N, results: &LayoutResults, f: &mut F)
+where N: TreeNode, F: FnMut(&N, &LayoutResult)
+{
+    for child in node.children() {
+        visit_laid_out(&child, results, f);
+    }
+
+    if let Some(result) = results.get(node) {
+        f(node, result);
+    }
+}
+
+/// Converts the computed layout of the node and its descendants into rectangles for the renderer
+fn collect_rectangles<T: Clone, N: TreeNode<Data = NodeData<T>>>(node: &N, results: &LayoutResults, rectangles: &mut Vec<Rect<T>>)
+{
+    visit_laid_out(node, results, &mut |node, result| {
+        rectangles.push(result.to_rect(node.borrow().clone()));
+    });
+}
+
+/// Calculates the bounds of a node in a flex layout
 /// current: the current node
-/// cur_z: The z-index, starts at 0 and increases. Is passed to OpenGL later
 /// sibling_count: How many siblings does this node have? (for flex distributing)
 /// sibling_count is 1 for root
+/// shared_sizes: Resolved sizes of the shared size groups
 /// **WARNING**: The root node have a width and a height (usually the case when
 /// you create the UiScreen via `.new()`)
-fn ui_screen_to_dp_list<T: Clone>(current: &NodeRef<Rect<T>>,  min_z: f32, max_z: f32,
-                           sibling_count: u32, sibling_index: u32,
-                           parent_width: f32, parent_height: f32,
-                           parent_offset_left: f32, parent_offset_top: f32,
-                           cur_offset_left: &mut f32, cur_offset_top: &mut f32)
--> Vec<Rect<T>>
+fn ui_screen_to_dp_list<T, N: TreeNode<Data = NodeData<T>>>(current: &N,
+                                                            sibling_count: u32, sibling_index: u32,
+                                                            parent_width: f32, parent_height: f32,
+                                                            parent_offset_left: f32, parent_offset_top: f32,
+                                                            cur_offset_left: &mut f32, cur_offset_top: &mut f32,
+                                                            shared_sizes: &SharedSizes)
+-> Bounds
 {
     use std::clone::Clone;
 
-    let mut rectangles = Vec::<Rect<T>>::new();
-
     let mut width = parent_width;
     let mut height = parent_height;
 
     if let Some(parent) = current.parent() {
-        if parent.borrow().data.flex_direction == FlexDirection::Row {
+        if parent.borrow().flex_direction == FlexDirection::Row {
             width /= (sibling_count - sibling_index) as f32;
         } else {
             height /= (sibling_count - sibling_index) as f32;
@@ -90,134 +434,775 @@ fn ui_screen_to_dp_list<T: Clone>(current: &NodeRef<Rect<T>>,  min_z: f32, max_z
     height -= *cur_offset_top;
 
     // correct width if there are hard constraints on max, min or exact width / height
-    if let Some(w) = current.borrow().data.width { width = w; }
-    if let Some(h) = current.borrow().data.height { height = h; }
+    if let Some(w) = current.borrow().width { width = w; }
+    if let Some(h) = current.borrow().height { height = h; }
 
     // if the width is greater than the maximal specified width, reduce
-    if let Some(max_width) = current.borrow().data.max_width {
+    if let Some(max_width) = current.borrow().max_width {
         if width > max_width {
             width = max_width;
         }
     }
 
-    if let Some(max_height) = current.borrow().data.max_height {
+    if let Some(max_height) = current.borrow().max_height {
         if height > max_height {
             height = max_height;
         }
     }
 
     // if the width is smaller than the minimal width, overflow the parent
-    if let Some(min_width) = current.borrow().data.min_width {
+    if let Some(min_width) = current.borrow().min_width {
         if width < min_width {
             width = min_width;
         }
     }
 
-    if let Some(min_height) = current.borrow().data.min_height {
+    if let Some(min_height) = current.borrow().min_height {
         if height < min_height {
             height = min_height;
         }
     }
 
+    // members of a shared size group are as wide / tall as the largest member
+    if let Some(w) = shared_sizes.width(&current.borrow().shared_width_group) { width = w; }
+    if let Some(h) = shared_sizes.height(&current.borrow().shared_height_group) { height = h; }
+
     // calculate space top + left
     let offset_top = cur_offset_top.clone() + parent_offset_top;
     let offset_left = cur_offset_left.clone() + parent_offset_left;
 
     // calculate offset for top and left
     if let Some(parent) = current.parent() {
-        if parent.borrow().data.flex_direction == FlexDirection::Row {
+        if parent.borrow().flex_direction == FlexDirection::Row {
             *cur_offset_left += width;
         } else {
             *cur_offset_top += height;
         }
     }
 
-    // z sorting is done by recursively dividing the range between max_z and
-    // min_z into segments proportional to the siblings - this way the children won't overlap the parent
+    Bounds::new(offset_left, offset_top, width, height)
+}
+
+/// z sorting is done by recursively dividing the range between max_z and
+/// min_z into segments proportional to the siblings - this way the children won't overlap the parent.
+/// The segments are counted from `min_z` (the z-index of the parent), not from 0,
+/// so that every subtree stays inside of the range of its parent.
+/// Returns the z-index of the node and the maximum z-index for its children.
+#[inline]
+fn z_index(min_z: f32, max_z: f32, sibling_count: u32, sibling_index: u32)
+-> (f32, f32)
+{
     let cur_z_stepping = (max_z - min_z) / (sibling_count as f32 + 1.0);
-    let z_index_current_node = cur_z_stepping * (sibling_index as f32 + 1.0);
+    let z_index_current_node = min_z + cur_z_stepping * (sibling_index as f32 + 1.0);
+    (z_index_current_node, z_index_current_node + cur_z_stepping)
+}
+
+/// Places a node at its final position (already calculated by the parent),
+/// then arranges the children of the node according to its `LayoutMode`.
+/// A clean node that is placed with the same key as in the previous layout
+/// keeps its previous layout, only moved to the new position.
+/// `parent_layout_mode` is the effective layout mode of the parent, `None` for the root.
+fn place_node<T, N, S>(current: &N, bounds: Bounds,
+                       z_index_current_node: f32, new_max_z: f32,
+                       parent_layout_mode: Option<LayoutMode>,
+                       ctx: &mut LayoutContext<S>)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
+    let key = LayoutKey {
+        width: bounds.width,
+        height: bounds.height,
+        z: z_index_current_node,
+        max_z: new_max_z,
+        parent_layout_mode,
+    };
+
+    // the cells of a table row are placed by the table (with the column widths of
+    // all rows), so a reused row would have to be placed again anyway. Clean
+    // cells are still reused, they only depend on their own size. The same goes
+    // for the nodes of a tree diagram, whose descendants are placed by the diagram.
+    let is_placed_by_parent = matches!(parent_layout_mode, Some(LayoutMode::Table) | Some(LayoutMode::TreeDiagram(_)));
+
+    if !is_placed_by_parent && !current.is_dirty() && ctx.previous.key(current) == Some(&key) {
+        if let Some(previous_bounds) = ctx.previous.get(current).map(|result| result.bounds) {
+            ctx.previous.move_subtree(current, &mut ctx.results);
+            ctx.results.translate_subtree(current, bounds.left - previous_bounds.left,
+                                          bounds.top - previous_bounds.top);
+            // the rotation is applied by the parent again
+            if let Some(result) = ctx.results.get_mut(current) {
+                result.rotation = 0.0;
+            }
+            return;
+        }
+    }
+
+    ctx.laid_out_count += 1;
+
+    let layout_mode = effective_layout_mode(current.borrow().layout_mode, parent_layout_mode);
+
+    match layout_mode {
+        // every child of a table is a row (see `table::arrange`), whatever
+        // its own layout mode is, the cells of the row are placed by the table.
+        // All descendants of a tree diagram are placed by the diagram.
+        _ if is_placed_by_parent => { },
+        LayoutMode::Table => {
+            let rows = table::arrange(current, bounds, ctx.measure_cache);
+            let row_count = rows.len() as u32;
+            for (row_index, row) in rows.into_iter().enumerate() {
+                let (row_z, row_max_z) = z_index(z_index_current_node, new_max_z, row_count, row_index as u32);
+                place_node(&row.row, row.bounds, row_z, row_max_z, Some(layout_mode), ctx);
+                let row_layout_mode = effective_layout_mode(row.row.borrow().layout_mode, Some(layout_mode));
+
+                let cell_count = row.cells.len() as u32;
+                for (cell_index, (cell, cell_bounds)) in row.cells.into_iter().enumerate() {
+                    let (cell_z, cell_max_z) = z_index(row_z, row_max_z, cell_count, cell_index as u32);
+                    place_node(&cell, cell_bounds, cell_z, cell_max_z, Some(row_layout_mode), ctx);
+                }
+            }
+        },
+        LayoutMode::Inline(ref inline_layout) => {
+            let children = inline_flow::arrange(current, bounds, inline_layout, ctx.measure_cache);
+            place_children(children, z_index_current_node, new_max_z, layout_mode, ctx);
+        },
+        LayoutMode::Masonry(ref masonry_layout) => {
+            let children = masonry::arrange(current, bounds, masonry_layout, ctx.measure_cache);
+            place_children(children, z_index_current_node, new_max_z, layout_mode, ctx);
+        },
+        LayoutMode::Treemap(ref treemap_layout) => {
+            let children = treemap::arrange(current, bounds, treemap_layout);
+            place_children(children, z_index_current_node, new_max_z, layout_mode, ctx);
+        },
+        LayoutMode::Radial(ref radial_layout) => {
+            let children = radial::arrange(current, bounds, radial_layout, ctx.measure_cache);
+            let children_count = children.len() as u32;
+            for (index, (node, child_bounds, angle)) in children.into_iter().enumerate() {
+                let (z, child_max_z) = z_index(z_index_current_node, new_max_z, children_count, index as u32);
+                place_node(&node, child_bounds, z, child_max_z, Some(layout_mode), ctx);
+                if radial_layout.rotate_children {
+                    if let Some(result) = ctx.results.get_mut(&node) {
+                        result.rotation = angle;
+                    }
+                }
+            }
+        },
+        LayoutMode::TreeDiagram(ref diagram_layout) => {
+            let roots = tree_diagram::arrange(current, bounds, diagram_layout);
+            place_diagram_nodes(roots, z_index_current_node, new_max_z, layout_mode, ctx);
+        },
+        LayoutMode::Flex | LayoutMode::TableRow | LayoutMode::TableCell { .. } => {
+            flex_children(current, bounds, z_index_current_node, new_max_z, layout_mode, ctx);
+        },
+    }
+
+    let content_box = current.children()
+        .filter_map(|child| ctx.results.get(&child).map(|result| result.content_box))
+        .fold(bounds, |content_box, child_box| content_box.union(&child_box));
+
+    ctx.results.insert(current, LayoutResult {
+        content_box,
+        .. LayoutResult::new(bounds, z_index_current_node)
+    });
+    ctx.results.insert_key(current, key);
+}
+
+/// Applies scrolling, radial rotations and transforms to the laid out node and its
+/// descendants. `parent_transform` is the combined transform of all ancestors.
+fn place_on_screen<T, N: TreeNode<Data = NodeData<T>>>(node: &N, parent_transform: &Transform2D, results: &mut LayoutResults)
+{
+    let data = node.borrow();
+    let (bounds, rotation) = match results.get(node) {
+        Some(result) => (result.bounds, result.rotation),
+        None => return,
+    };
 
-    // construct rectangle and repeat for children
-    // mark if min-width or max-width has modified the remaining width for siblings
-    let data = current.borrow().data.clone();
-    let cur_rect = Rect::new_wh(offset_left, offset_top, width as f32, height as f32, z_index_current_node, data);
+    // transforms are applied to the node and all of its descendants
+    let mut transform = Transform2D::identity();
+    if let Some(node_transform) = data.transform {
+        let (origin_x, origin_y) = data.transform_origin;
+        transform = node_transform.around(bounds.left + bounds.width * origin_x,
+                                          bounds.top + bounds.height * origin_y);
+    }
+    if rotation != 0.0 {
+        let rotation = Transform2D::rotation(rotation)
+            .around(bounds.left + bounds.width * 0.5, bounds.top + bounds.height * 0.5);
+        transform = transform.then(&rotation);
+    }
+    let transform = transform.then(parent_transform);
+
+    if let Some(result) = results.get_mut(node) {
+        result.set_screen_transform(&transform);
+    }
+
+    // scrolling moves the content of the node, not the node itself
+    let children_transform = if data.overflow == Overflow::Scroll && data.scroll_offset != (0.0, 0.0) {
+        let (scroll_x, scroll_y) = data.scroll_offset;
+        Transform2D::translation(-scroll_x, -scroll_y).then(&transform)
+    } else {
+        transform
+    };
+
+    for child in node.children() {
+        place_on_screen(&child, &children_transform, results);
+    }
+}
+
+/// Returns the layout mode of a node, taking nested treemaps into account:
+/// `Flex` nodes inside of a nested treemap are subdivided like their parent.
+/// `parent_layout_mode` is the effective layout mode of the parent.
+#[inline]
+fn effective_layout_mode(layout_mode: LayoutMode, parent_layout_mode: Option<LayoutMode>)
+-> LayoutMode
+{
+    match (layout_mode, parent_layout_mode) {
+        (LayoutMode::Flex, Some(LayoutMode::Treemap(treemap_layout))) if treemap_layout.nested => LayoutMode::Treemap(treemap_layout),
+        _ => layout_mode,
+    }
+}
+
+/// Places children whose position was already calculated by the layout mode of their parent
+fn place_children<T, N, S>(children: Vec<(N, Bounds)>,
+                           z_index_parent: f32, max_z: f32,
+                           parent_layout_mode: LayoutMode,
+                           ctx: &mut LayoutContext<S>)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
+    let children_count = children.len() as u32;
+
+    let children = children.into_iter().enumerate().map(|(index, (node, bounds))| {
+        let (z, child_max_z) = z_index(z_index_parent, max_z, children_count, index as u32);
+        (node, bounds, z, child_max_z)
+    }).collect();
+
+    let strategy = ctx.strategy;
+    strategy.place_subtrees(children, parent_layout_mode, ctx);
+}
+
+/// Places the nodes of a tree diagram, every node gets a part of the z-range of
+/// its parent in the diagram. The children are placed first, so that they end
+/// up in the content box of their parent.
+fn place_diagram_nodes<T, N, S>(nodes: Vec<DiagramNode<N>>,
+                                z_index_parent: f32, max_z: f32,
+                                diagram_layout_mode: LayoutMode,
+                                ctx: &mut LayoutContext<S>)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
+    let nodes_count = nodes.len() as u32;
+    for (index, diagram_node) in nodes.into_iter().enumerate() {
+        let (z, child_max_z) = z_index(z_index_parent, max_z, nodes_count, index as u32);
+        place_diagram_nodes(diagram_node.children, z, child_max_z, diagram_layout_mode, ctx);
+        place_node(&diagram_node.node, diagram_node.bounds, z, child_max_z, Some(diagram_layout_mode), ctx);
+    }
+}
+
+/// Places sibling subtrees whose bounds and z-ranges are already known. The
+/// subtrees don't depend on each other, so they can be laid out in any order.
+trait PlaceSubtrees<N>: Copy + Sized {
+    /// Places every `(node, bounds, z, max_z)` with `place_node`
+    fn place_subtrees(self, children: Vec<(N, Bounds, f32, f32)>, parent_layout_mode: LayoutMode,
+                      ctx: &mut LayoutContext<Self>);
+}
+
+/// Places all subtrees one after another on the calling thread
+#[derive(Debug, Copy, Clone)]
+struct Sequential;
+
+impl<T, N: TreeNode<Data = NodeData<T>>> PlaceSubtrees<N> for Sequential {
+    fn place_subtrees(self, children: Vec<(N, Bounds, f32, f32)>, parent_layout_mode: LayoutMode,
+                      ctx: &mut LayoutContext<Self>)
+    {
+        for (node, bounds, z, max_z) in children {
+            place_node(&node, bounds, z, max_z, Some(parent_layout_mode), ctx);
+        }
+    }
+}
+
+/// Places the large subtrees on scoped worker threads, every worker gets the
+/// previous results and cached sizes of its subtrees and returns its results
+impl<T, N: TreeNode<Data = NodeData<T>> + Send> PlaceSubtrees<N> for ParallelLayout {
+    fn place_subtrees(self, children: Vec<(N, Bounds, f32, f32)>, parent_layout_mode: LayoutMode,
+                      ctx: &mut LayoutContext<Self>)
+    {
+        let min_size = self.min_subtree_size;
+        let (large, small): (Vec<_>, Vec<_>) = children.into_iter().partition(|child| {
+            self.threads > 1 && child.0.descendants().take(min_size).count() >= min_size
+        });
+
+        // the descendants of a small subtree are even smaller, so they are not counted again
+        ctx.strategy = ParallelLayout { threads: 1, .. self };
+        for (node, bounds, z, max_z) in small {
+            place_node(&node, bounds, z, max_z, Some(parent_layout_mode), ctx);
+        }
+        ctx.strategy = self;
+
+        if large.len() < 2 {
+            for (node, bounds, z, max_z) in large {
+                place_node(&node, bounds, z, max_z, Some(parent_layout_mode), ctx);
+            }
+            return;
+        }
+
+        // the calling thread lays out the first chunk of subtrees, the
+        // remaining threads are split between the workers for nested subtrees
+        let workers = (large.len() - 1).min(self.threads - 1);
+        let chunk_size = large.len().div_ceil(workers + 1);
+        let nested = ParallelLayout { threads: (self.threads / (workers + 1)).max(1), .. self };
+
+        let mut chunks = large.chunks(chunk_size).map(<[_]>::to_vec);
+        let own_chunk = chunks.next().unwrap_or_default();
+        let worker_chunks = chunks.map(|chunk| {
+            let mut previous = LayoutResults::new();
+            let mut measure_cache = MeasureCache::new();
+            for (node, ..) in &chunk {
+                ctx.previous.move_subtree(node, &mut previous);
+                ctx.measure_cache.move_subtree(node, &mut measure_cache);
+            }
+            (chunk, previous, measure_cache)
+        }).collect::<Vec<_>>();
+
+        let shared_sizes = ctx.shared_sizes;
+        thread::scope(|scope| {
+            let handles = worker_chunks.into_iter().map(|(chunk, previous, mut measure_cache)| {
+                scope.spawn(move || {
+                    let mut worker_ctx = LayoutContext {
+                        shared_sizes,
+                        previous,
+                        results: LayoutResults::new(),
+                        measure_cache: &mut measure_cache,
+                        laid_out_count: 0,
+                        strategy: nested,
+                    };
+                    for (node, bounds, z, max_z) in chunk {
+                        place_node(&node, bounds, z, max_z, Some(parent_layout_mode), &mut worker_ctx);
+                    }
+                    let LayoutContext { results, laid_out_count, .. } = worker_ctx;
+                    (results, laid_out_count, measure_cache)
+                })
+            }).collect::<Vec<_>>();
 
-    // flip y axis and update self (for external libraries)
-    // this step can be avoided
-    *current.borrow_mut() = cur_rect.clone();
+            ctx.strategy = nested;
+            for (node, bounds, z, max_z) in own_chunk {
+                place_node(&node, bounds, z, max_z, Some(parent_layout_mode), ctx);
+            }
+            ctx.strategy = self;
 
+            for handle in handles {
+                let (results, laid_out_count, measure_cache) = handle.join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload));
+                ctx.results.append(results);
+                ctx.laid_out_count += laid_out_count;
+                ctx.measure_cache.append(measure_cache);
+            }
+        });
+    }
+}
+
+/// Stacks the children of a node along its `flex_direction`
+fn flex_children<T, N, S>(current: &N, bounds: Bounds,
+                          z_index_current_node: f32, new_max_z: f32,
+                          layout_mode: LayoutMode,
+                          ctx: &mut LayoutContext<S>)
+where N: TreeNode<Data = NodeData<T>>, S: PlaceSubtrees<N>
+{
     // iterate children nodes
     let children_count = current.children().count();
 
-    let self_width = width.clone();
-    let self_height = height.clone();
-    let new_max_z = z_index_current_node + cur_z_stepping;
+    let self_width = bounds.width;
+    let self_height = bounds.height;
 
-    let new_offset_left = offset_left.clone();
-    let new_offset_top = offset_top.clone();
+    let new_offset_left = bounds.left;
+    let new_offset_top = bounds.top;
     let mut offset_top_zeroed = 0.0;
     let mut offset_left_zeroed = 0.0;
 
+    // the bounds of a child only depend on its preceding siblings, not on their layout
+    let mut children = Vec::with_capacity(children_count);
     for (index, node) in current.children().enumerate() {
-        rectangles.append(&mut ui_screen_to_dp_list::<T>(&node, z_index_current_node, new_max_z,
-                                                     children_count as u32, index as u32,
-                                                     self_width, self_height,
-                                                     new_offset_left, new_offset_top,
-                                                     &mut offset_left_zeroed, &mut offset_top_zeroed));
+        let bounds = ui_screen_to_dp_list(&node, children_count as u32, index as u32,
+                                          self_width, self_height,
+                                          new_offset_left, new_offset_top,
+                                          &mut offset_left_zeroed, &mut offset_top_zeroed,
+                                          ctx.shared_sizes);
+        children.push((node, bounds));
+    }
+    place_children(children, z_index_current_node, new_max_z, layout_mode, ctx);
+
+    let (flex_direction, align_items) = {
+        let data = current.borrow();
+        (data.flex_direction, data.align_items)
+    };
+
+    if flex_direction == FlexDirection::Row && align_items == AlignItems::Baseline {
+        align_children_to_baseline(current, &mut ctx.results);
+    }
+}
+
+#[test]
+fn shared_width_group_aligns_labels_across_rows() {
+    let mut ui_screen = UiScreen::new(400.0, 200.0, NodeData::empty(FlexDirection::Column, 0))
+                            .with_root_as_column();
+
+    for (index, label_width) in [120.0, 80.0].iter().enumerate() {
+        let row = NodeRef::new(NodeData::empty(FlexDirection::Row, 0));
+        let mut label = NodeData::empty(FlexDirection::Row, index + 1).with_shared_width_group("label");
+        label.max_width = Some(*label_width);
+        row.append(NodeRef::new(label));
+        row.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 0)));
+        ui_screen.root.append(row);
     }
 
-    rectangles.push(cur_rect);
+    let rectangles = ui_screen.into_rectangles(400.0, 200.0);
+    let label_widths = rectangles.iter()
+        .filter(|rect| rect.data.data != 0)
+        .map(|rect| rect.x[1] - rect.x[0])
+        .collect::<Vec<_>>();
 
-    return rectangles;
+    assert_eq!(label_widths, vec![120.0, 120.0]);
 }
 
-// without rendering: 36 ns / iter
-// with rendering (use "vblank_mode=0 cargo bench") to get correct results: ~1.9 ms
-#[bench]
-fn bench_ui_screen_layout_simple(b: &mut test::Bencher) {
+#[test]
+fn baseline_alignment_in_row() {
+    let ui_screen_data = NodeData::empty(FlexDirection::Row, 0).with_align_items(AlignItems::Baseline);
+    let mut ui_screen = UiScreen::new(400.0, 100.0, ui_screen_data);
+
+    let small_label = NodeData::empty(FlexDirection::Row, 1).with_baseline(10.0);
+    let big_label = NodeData::empty(FlexDirection::Row, 2).with_baseline(30.0);
+    ui_screen.root.append(NodeRef::new(small_label));
+    ui_screen.root.append(NodeRef::new(big_label));
 
-    extern crate rand;
+    let rectangles = ui_screen.into_rectangles(400.0, 100.0);
+    let small = rectangles.iter().find(|rect| rect.data.data == 1).unwrap();
+    let big = rectangles.iter().find(|rect| rect.data.data == 2).unwrap();
 
-    use input;
+    assert_eq!(small.y[0] + 10.0, big.y[0] + 30.0);
+    assert_eq!(::baseline::first_baseline(&ui_screen.root, ui_screen.layout_results()), Some(30.0));
+}
 
-    const INITIAL_WIDTH: u32 = 600;
-    const INITIAL_HEIGHT: u32 = 800;
-    let mut window_state = input::WindowState::new(INITIAL_WIDTH, INITIAL_HEIGHT);
+#[test]
+fn transform_is_inherited_by_children() {
+    use transform::Transform2D;
 
-    // Construct the explorer UI
-    let mut ui_screen = UiScreen::new(INITIAL_WIDTH, INITIAL_HEIGHT)
-                            .with_root_as_column();
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+
+    // scale the panel by 2 around its top left corner
+    let panel = NodeData::empty(FlexDirection::Row, 1)
+        .with_transform(Transform2D::scale(2.0, 2.0))
+        .with_transform_origin(0.0, 0.0);
+    let panel = NodeRef::new(panel);
+    let mut child = NodeData::empty(FlexDirection::Row, 2);
+    child.width = Some(10.0);
+    panel.append(NodeRef::new(child));
+    ui_screen.root.append(panel);
+
+    let rectangles = ui_screen.into_rectangles(100.0, 100.0);
+    let child = rectangles.iter().find(|rect| rect.data.data == 2).unwrap();
+
+    assert_eq!(child.x, [0.0, 20.0, 0.0, 20.0]);
+    assert_eq!(child.y, [0.0, 0.0, 200.0, 200.0]);
+}
+
+#[test]
+fn relayout_does_not_change_the_result() {
+    use transform::Transform2D;
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let panel = NodeRef::new(NodeData::empty(FlexDirection::Row, 1).with_transform(Transform2D::rotation(30.0)));
+    panel.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 2)));
+    ui_screen.root.append(panel.clone());
+
+    ui_screen.into_rectangles(100.0, 100.0);
+    let first = *ui_screen.layout(&panel).unwrap();
+    ui_screen.into_rectangles(100.0, 100.0);
+
+    assert_eq!(*ui_screen.layout(&panel).unwrap(), first);
+    assert_eq!(first.bounds, Bounds::new(0.0, 0.0, 100.0, 100.0));
+    assert_eq!(panel.borrow().width, None);
+}
+
+#[test]
+fn records_do_not_need_clone() {
+    #[derive(Debug)]
+    struct Callback(u32);
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, Callback(0)));
+    ui_screen.root.append(NodeRef::new(NodeData::empty(FlexDirection::Row, Callback(1))));
+    ui_screen.root.append(NodeRef::new(NodeData::empty(FlexDirection::Row, Callback(2))));
+
+    let records = ui_screen.into_records(100.0, 100.0);
+    assert_eq!(records.iter().map(|r| r.node.borrow().data.0).collect::<Vec<_>>(), vec![1, 2, 0]);
+    assert_eq!(records[1].layout.bounds, Bounds::new(50.0, 0.0, 50.0, 100.0));
+}
+
+#[test]
+fn only_dirty_subtrees_are_laid_out_again() {
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let mut fixed = NodeData::empty(FlexDirection::Row, 1);
+    fixed.width = Some(20.0);
+    let first = NodeRef::new(fixed.clone());
+    let second = NodeRef::new(fixed);
+    let grandchild = NodeRef::new(NodeData::empty(FlexDirection::Row, 2));
+    second.append(grandchild.clone());
+    ui_screen.root.append(first.clone());
+    ui_screen.root.append(second.clone());
+
+    ui_screen.compute_layout(100.0, 100.0);
+    assert_eq!(ui_screen.laid_out_count(), 4);
+    assert!(!ui_screen.root.is_dirty());
+
+    // nothing changed
+    ui_screen.compute_layout(100.0, 100.0);
+    assert_eq!(ui_screen.laid_out_count(), 0);
+
+    // the second node is moved, but keeps its size
+    first.borrow_mut().width = Some(30.0);
+    assert!(ui_screen.root.is_dirty() && !second.is_dirty());
+    ui_screen.compute_layout(100.0, 100.0);
+    assert_eq!(ui_screen.laid_out_count(), 2);
+    assert_eq!(ui_screen.layout(&grandchild).unwrap().bounds, Bounds::new(30.0, 0.0, 20.0, 100.0));
+    assert_eq!(ui_screen.layout(&grandchild).unwrap().x, [30.0, 50.0, 30.0, 50.0]);
+}
+
+#[test]
+fn arena_tree_has_the_same_layout() {
+    let mut fixed = NodeData::empty(FlexDirection::Column, 1);
+    fixed.width = Some(20.0);
+
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let panel = NodeRef::new(fixed.clone());
+    panel.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 2)));
+    ui_screen.root.append(panel);
+    ui_screen.root.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 3)));
+
+    let mut tree = ArenaTree::new(NodeData::empty(FlexDirection::Row, 0));
+    let panel = tree.arena.new_node(fixed);
+    let child = tree.arena.new_node(NodeData::empty(FlexDirection::Row, 2));
+    let last = tree.arena.new_node(NodeData::empty(FlexDirection::Row, 3));
+    tree.arena.append(panel, child);
+    tree.arena.append(tree.root, panel);
+    tree.arena.append(tree.root, last);
+    let mut arena_screen = UiScreen::from_tree(100.0, 100.0, tree);
+
+    let corners = |rectangles: Vec<Rect<u32>>| rectangles.iter().map(|r| (r.x, r.y, r.z)).collect::<Vec<_>>();
+    assert_eq!(corners(arena_screen.into_rectangles(100.0, 100.0)), corners(ui_screen.into_rectangles(100.0, 100.0)));
+    assert_eq!(arena_screen.hit_test(10.0, 10.0).unwrap().path, vec![arena_screen.root.root, panel, child]);
+
+    // changing the data through the arena marks the node as dirty
+    arena_screen.root.arena[panel].width = Some(30.0);
+    arena_screen.compute_layout(100.0, 100.0);
+    assert_eq!(arena_screen.laid_out_count(), 4);
+    assert_eq!(arena_screen.layout(&last).unwrap().bounds, Bounds::new(30.0, 0.0, 70.0, 100.0));
+}
+
+#[test]
+fn arena_screen_is_laid_out_on_a_worker_thread() {
+    use std::thread;
+    use std::sync::mpsc;
+    use arena::NodeId;
+
+    fn assert_send_sync<S: Send + Sync>() { }
+    assert_send_sync::<UiScreen<u32, ArenaTree<u32>>>();
+    assert_send_sync::<LayoutResults>();
+    assert_send_sync::<LayoutRecord<NodeId>>();
+    assert_send_sync::<LayoutSnapshot<NodeId>>();
+
+    let (sender, receiver) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        let mut ui_screen = UiScreen::new_in_arena(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+        let root = ui_screen.root.root;
+        let child = ui_screen.root.arena.new_node(NodeData::empty(FlexDirection::Row, 1));
+        ui_screen.root.arena.append(root, child);
+        sender.send(ui_screen.into_records(100.0, 100.0)).unwrap();
+        ui_screen
+    });
+
+    let records = receiver.recv().unwrap();
+    let ui_screen = worker.join().unwrap();
+    let child = ui_screen.root.arena.first_child(ui_screen.root.root).unwrap();
+    assert_eq!(records.len(), 2);
+    let record = records.iter().find(|record| record.node == child).unwrap();
+    assert_eq!(record.layout.bounds, Bounds::new(0.0, 0.0, 100.0, 100.0));
+}
+
+#[test]
+fn parallel_layout_matches_sequential_layout() {
+    use node_data::{MasonryLayout, MasonryColumns};
+
+    // a dashboard with independent panels, one of them measures its children
+    let mut tree = ArenaTree::new(NodeData::empty(FlexDirection::Row, 0));
+    for panel_index in 0..4 {
+        let mut panel = NodeData::empty(FlexDirection::Column, panel_index);
+        if panel_index == 3 {
+            panel.layout_mode = LayoutMode::Masonry(MasonryLayout { columns: MasonryColumns::Count(2), gap: 4.0 });
+        }
+        let panel = tree.arena.new_node(panel);
+        tree.arena.append(tree.root, panel);
+        for row_index in 0..5 {
+            let row = tree.arena.new_node(NodeData::empty(FlexDirection::Row, row_index));
+            tree.arena.append(panel, row);
+            for cell_index in 0..4 {
+                let mut cell = NodeData::empty(FlexDirection::Row, cell_index);
+                cell.height = Some(10.0 + cell_index as f32);
+                let cell = tree.arena.new_node(cell);
+                tree.arena.append(row, cell);
+            }
+        }
+    }
+
+    let options = ParallelLayout { threads: 4, min_subtree_size: 8 };
+    let mut sequential = UiScreen::from_tree(800.0, 600.0, tree.clone());
+    let mut parallel = UiScreen::from_tree(800.0, 600.0, tree);
+
+    let check = |sequential: &mut UiScreen<usize, ArenaTree<usize>>, parallel: &mut UiScreen<usize, ArenaTree<usize>>| {
+        sequential.compute_layout(800.0, 600.0);
+        parallel.compute_layout_parallel(800.0, 600.0, &options);
+        assert_eq!(parallel.laid_out_count(), sequential.laid_out_count());
+        assert_eq!(parallel.layout_results().len(), sequential.layout_results().len());
+        for node in parallel.root.arena.descendants(parallel.root.root) {
+            assert_eq!(parallel.layout(&node), sequential.layout(&node));
+        }
+    };
+
+    check(&mut sequential, &mut parallel);
+    assert_eq!(parallel.laid_out_count(), 105);
+
+    // only the changed panel is laid out again, the others are reused by the workers
+    for ui_screen in [&mut sequential, &mut parallel] {
+        let panel = ui_screen.root.arena.first_child(ui_screen.root.root).unwrap();
+        ui_screen.root.arena[panel].width = Some(100.0);
+    }
+    check(&mut sequential, &mut parallel);
+    assert!(parallel.laid_out_count() < 105);
+}
+
+#[test]
+fn flex_children_of_a_table_are_rows() {
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let table = NodeRef::new(NodeData::empty(FlexDirection::Column, 1).with_layout_mode(LayoutMode::Table));
+    // a row without `LayoutMode::TableRow`
+    let row = NodeRef::new(NodeData::empty(FlexDirection::Row, 2));
+    for index in 0..2 {
+        let mut cell = NodeData::empty(FlexDirection::Row, 3 + index);
+        cell.min_width = Some(20.0);
+        cell.max_width = Some(20.0 + 10.0 * index as f32);
+        row.append(NodeRef::new(cell));
+    }
+    table.append(row.clone());
+    ui_screen.root.append(table.clone());
+
+    ui_screen.compute_layout(100.0, 100.0);
+    // the cells are only laid out once, by the table
+    assert_eq!(ui_screen.laid_out_count(), 5);
+
+    let rows = table::arrange(&table, Bounds::new(0.0, 0.0, 100.0, 100.0), &mut MeasureCache::new());
+    let row_result = *ui_screen.layout(&row).unwrap();
+    for (cell, cell_bounds) in &rows[0].cells {
+        let cell_result = ui_screen.layout(cell).unwrap();
+        assert_eq!(cell_result.bounds, *cell_bounds);
+        assert!(cell_result.z > row_result.z);
+    }
+
+    // rows are never reused, but their clean cells are
+    let second_row = NodeRef::new(NodeData::empty(FlexDirection::Row, 5));
+    let changed_cell = NodeRef::new(NodeData::empty(FlexDirection::Row, 6));
+    second_row.append(changed_cell.clone());
+    table.append(second_row);
+    ui_screen.compute_layout(100.0, 100.0);
+    changed_cell.borrow_mut().data = 7;
+    ui_screen.compute_layout(100.0, 100.0);
+    // root, table, both rows and the changed cell
+    assert_eq!(ui_screen.laid_out_count(), 5);
+
+    let rows = table::arrange(&table, Bounds::new(0.0, 0.0, 100.0, 100.0), &mut MeasureCache::new());
+    for (cell, cell_bounds) in rows.iter().flat_map(|row| row.cells.iter()) {
+        assert_eq!(ui_screen.layout(cell).unwrap().bounds, *cell_bounds);
+    }
+}
+
+#[test]
+fn flex_nodes_in_a_nested_treemap_are_subdivided() {
+    use node_data::TreemapLayout;
+    use intrinsic::max_content_width;
+
+    let area = |ui_screen: &UiScreen<usize>, node: &NodeRef<NodeData<usize>>| {
+        let bounds = ui_screen.layout(node).unwrap().bounds;
+        bounds.width * bounds.height
+    };
+
+    for nested in [true, false] {
+        let treemap_layout = TreemapLayout { padding: 0.0, nested };
+        let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0)
+                                                            .with_layout_mode(LayoutMode::Treemap(treemap_layout)));
+        ui_screen.root.append(NodeRef::new(NodeData::empty(FlexDirection::Row, 1).with_weight(4.0)));
+        let folder = NodeRef::new(NodeData::empty(FlexDirection::Row, 2));
+        let large = NodeRef::new(NodeData::empty(FlexDirection::Row, 3).with_weight(3.0));
+        let small = NodeRef::new(NodeData::empty(FlexDirection::Row, 4).with_weight(1.0));
+        folder.append(large.clone());
+        folder.append(small.clone());
+        ui_screen.root.append(folder.clone());
+        ui_screen.compute_layout(100.0, 100.0);
+
+        // the folder gets half of the area, its children are either subdivided
+        // by weight or stacked like in any other flex node
+        assert!((area(&ui_screen, &folder) - 5000.0).abs() < 0.01);
+        let expected_ratio = if nested { 3.0 } else { 1.0 };
+        assert!((area(&ui_screen, &large) / area(&ui_screen, &small) - expected_ratio).abs() < 0.01);
+    }
+
+    // a treemap has no content size besides its padding
+    let treemap = NodeRef::new(NodeData::empty(FlexDirection::Row, 0)
+                                   .with_layout_mode(LayoutMode::Treemap(TreemapLayout { padding: 5.0, nested: false })));
+    let mut child = NodeData::empty(FlexDirection::Row, 1).with_weight(1.0);
+    child.width = Some(50.0);
+    treemap.append(NodeRef::new(child));
+    assert_eq!(max_content_width(&treemap), 10.0);
+}
+
+#[test]
+fn children_are_stacked_above_their_parent() {
+    let mut ui_screen = UiScreen::new(100.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let first = NodeRef::new(NodeData::empty(FlexDirection::Row, 1));
+    let grandchild = NodeRef::new(NodeData::empty(FlexDirection::Row, 2));
+    let second = NodeRef::new(NodeData::empty(FlexDirection::Row, 3));
+    first.append(grandchild.clone());
+    ui_screen.root.append(first.clone());
+    ui_screen.root.append(second.clone());
+    ui_screen.compute_layout(100.0, 100.0);
+
+    let z = |node: &NodeRef<NodeData<usize>>| ui_screen.layout(node).unwrap().z;
+
+    // every node is inside of the z-range of its parent, so it is drawn above the
+    // parent, and a later sibling is drawn above the whole subtree of an earlier one
+    assert!(z(&ui_screen.root) < z(&first));
+    assert!(z(&first) < z(&grandchild));
+    assert!(z(&grandchild) < z(&second));
+    assert!(z(&second) < 1.0);
+
+    // three children in the range of a parent at 0.5: segments of 0.125 above 0.5
+    assert_eq!(z_index(0.5, 1.0, 3, 0), (0.625, 0.75));
+    assert_eq!(z_index(0.5, 1.0, 3, 2), (0.875, 1.0));
+}
+
+#[test]
+fn popovers_need_a_laid_out_anchor() {
+    use popover::PopoverSide;
+
+    let mut ui_screen = UiScreen::new(200.0, 100.0, NodeData::empty(FlexDirection::Row, 0));
+    let mut button = NodeData::empty(FlexDirection::Row, 1);
+    button.width = Some(40.0);
+    button.height = Some(20.0);
+    let button = NodeRef::new(button);
+    ui_screen.root.append(button.clone());
+    ui_screen.into_rectangles(200.0, 100.0);
+
+    let mut menu = NodeData::empty(FlexDirection::Row, 2);
+    menu.width = Some(60.0);
+    menu.height = Some(30.0);
+    let menu = NodeRef::new(menu);
+
+    let (placement, rectangles) = ui_screen.layout_popover(&button, &menu, &PopoverOptions::default()).unwrap();
+    assert_eq!(placement.side, PopoverSide::Bottom);
+    assert_eq!(rectangles.len(), 1);
+    assert!(rectangles[0].z > 1.0 && rectangles[0].z < 2.0);
 
-    // Top bar, 100 - 200 pixels tall, stretches full window
-    let top_bar_wrapper = NodeRef::new(NodeData::new(
-            None, None, None, None, None, None,
-            FlexDirection::Column, DebugColor::green()));
-
-    // Main explorer view, stretches all sides
-    let explorer_wrapper = NodeRef::new(NodeData::new(
-        None, None, None, None, None, None,
-        FlexDirection::Row, DebugColor::blue()));
-
-            // navigation side bar
-            let navigation_pane = NodeRef::new(NodeData::new(
-                Some(500.0), None, Some(700.0), None, None, None,
-                FlexDirection::Column, DebugColor::red()));
-
-            // file list
-            let file_list_view = NodeRef::new(NodeData::new(
-                None, None, None, None, Some(50.0), None,
-                FlexDirection::Column, DebugColor::blue()));
-
-    // drawing order
-    explorer_wrapper.append(navigation_pane);
-    explorer_wrapper.append(file_list_view);
-    ui_screen.root.append(top_bar_wrapper);
-    ui_screen.root.append(explorer_wrapper);
-
-    b.iter(|| {
-            let event = glium::glutin::Event::Resized(rand::random::<u32>(), rand::random::<u32>());
-            input::handle_event(&event, &mut window_state, &mut ui_screen);
-            let _ = ui_screen.into_rectangles();
-        }
-    )
+    // an anchor that was never laid out has no position to place the popover at
+    let detached = NodeRef::new(NodeData::empty(FlexDirection::Row, 3));
+    assert!(ui_screen.layout_popover(&detached, &menu, &PopoverOptions::default()).is_none());
 }